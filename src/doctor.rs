@@ -0,0 +1,59 @@
+use std::fmt;
+
+/// a setup check of `Mdblog::doctor`
+#[derive(Debug)]
+pub struct Diagnosis {
+    /// what is checked
+    pub name: String,
+    /// the problems found, empty if the check passed
+    pub problems: Vec<String>,
+    /// how to fix the problems
+    pub hint: String,
+}
+
+impl Diagnosis {
+    /// create a check of `name` with the remediation `hint`.
+    pub fn new(name: &str, hint: &str) -> Diagnosis {
+        Diagnosis {
+            name: name.to_string(),
+            problems: Vec::new(),
+            hint: hint.to_string(),
+        }
+    }
+
+    /// check the check passed.
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// blog setup diagnostics, a checklist of non-destructive checks
+#[derive(Debug, Default)]
+pub struct Doctor {
+    /// the checks in running order
+    pub checks: Vec<Diagnosis>,
+}
+
+impl Doctor {
+    /// the number of failed checks.
+    pub fn failed(&self) -> usize {
+        self.checks.iter().filter(|c| !c.is_ok()).count()
+    }
+}
+
+impl fmt::Display for Doctor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for check in &self.checks {
+            if check.is_ok() {
+                writeln!(f, "[ok]   {}", check.name)?;
+                continue;
+            }
+            writeln!(f, "[fail] {}", check.name)?;
+            for problem in &check.problems {
+                writeln!(f, "       {}", problem)?;
+            }
+            writeln!(f, "       hint: {}", check.hint)?;
+        }
+        Ok(())
+    }
+}
@@ -1,5 +1,6 @@
 use std::error::Error as StdError;
-use std::path::PathBuf;
+use std::fmt;
+use std::path::{Path, PathBuf};
 
 use derive_more::{Display, From};
 
@@ -36,12 +37,36 @@ pub enum Error {
     /// toml export error
     #[display(fmt = "toml export error")]
     TomlExport(toml::ser::Error),
+    /// json export error
+    #[display(fmt = "json export error")]
+    JsonExport(serde_json::Error),
     /// path expand error
     #[display(fmt = "path expand error")]
     PathExpend(shellexpand::LookupError<std::env::VarError>),
     /// post head parse error
     #[display(fmt = "{:?}: post head parse error, please use yaml grammar", _1)]
     PostHeadPaser(serde_yaml::Error, PathBuf),
+    /// standalone post headers parse error
+    #[from(ignore)]
+    #[display(fmt = "post headers parse error, please use yaml grammar")]
+    HeadersParse(serde_yaml::Error),
+
+    /// file read error
+    #[from(ignore)]
+    #[display(fmt = "reading file {:?} failed", _0)]
+    FileRead(PathBuf, std::io::Error),
+    /// file write error
+    #[from(ignore)]
+    #[display(fmt = "writing file {:?} failed", _0)]
+    FileWrite(PathBuf, std::io::Error),
+    /// file copy error
+    #[from(ignore)]
+    #[display(fmt = "copying file {:?} to {:?} failed", _0, _1)]
+    FileCopy(PathBuf, PathBuf, std::io::Error),
+    /// directory create error
+    #[from(ignore)]
+    #[display(fmt = "creating directory {:?} failed", _0)]
+    DirCreate(PathBuf, std::io::Error),
 
     /// blog root directory already exists error
     #[from(ignore)]
@@ -70,6 +95,14 @@ pub enum Error {
     #[from(ignore)]
     #[display(fmt = "blog theme {:?} not found", _0)]
     ThemeNotFound(String),
+    /// blog theme existed error
+    #[from(ignore)]
+    #[display(fmt = "blog theme {:?} existed", _0)]
+    ThemeExisted(String),
+    /// blog theme archive missing required file error
+    #[from(ignore)]
+    #[display(fmt = "theme archive {:?} has no {}", _0, _1)]
+    ThemeArchiveInvalid(PathBuf, String),
     /// post must has two parts error
     #[from(ignore)]
     #[display(
@@ -85,6 +118,103 @@ pub enum Error {
     #[from(ignore)]
     #[display(fmt = "post {:?} body part is empty", _0)]
     PostNoBody(PathBuf),
+    /// post header unknown error
+    #[from(ignore)]
+    #[display(fmt = "post {:?} has unknown header {:?}", _0, _1)]
+    PostHeaderUnknown(PathBuf, String),
+    /// no post rendered error
+    #[from(ignore)]
+    #[display(fmt = "no post is rendered from {:?}", _0)]
+    PostsEmpty(PathBuf),
+    /// post output file duplicated error
+    #[from(ignore)]
+    #[display(fmt = "post {:?} and post {:?} have the same url", _0, _1)]
+    PostSlugDuplicated(PathBuf, PathBuf),
+    /// post internal link broken error
+    #[from(ignore)]
+    #[display(fmt = "post {:?} has broken internal link {:?}", _0, _1)]
+    PostLinkBroken(PathBuf, String),
+    /// post `post:<slug>` link matches no post error
+    #[from(ignore)]
+    #[display(fmt = "post {:?} link {:?} matches no post", _0, _1)]
+    PostLinkUnresolved(PathBuf, String),
+    /// timezone setting format error
+    #[from(ignore)]
+    #[display(fmt = "timezone {:?} format error: must be `local`, `UTC` or a fixed offset like `+08:00`", _0)]
+    TimezoneInvalid(String),
+    /// post alias collision error
+    #[from(ignore)]
+    #[display(fmt = "alias {:?} of post {:?} collides with post {:?}", _0, _1, _2)]
+    PostAliasCollision(String, PathBuf, PathBuf),
+    /// post alias outside the build directory error
+    #[from(ignore)]
+    #[display(fmt = "alias {:?} of post {:?} must be a path inside the build directory", _0, _1)]
+    PostAliasInvalid(String, PathBuf),
+    /// post alias overwriting a generated file error
+    #[from(ignore)]
+    #[display(fmt = "alias {:?} of post {:?} overwrites the generated {:?}", _0, _1, _2)]
+    PostAliasOverwrite(String, PathBuf, PathBuf),
+    /// tags file parse error
+    #[from(ignore)]
+    #[display(fmt = "tags file {:?} parse error", _0)]
+    TagsParse(PathBuf, toml::de::Error),
+    /// build since date invalid error
+    #[from(ignore)]
+    #[display(fmt = "invalid date {:?}, expected format YYYY-MM-DD", _0)]
+    SinceDateInvalid(String),
+    /// git command failed error
+    #[from(ignore)]
+    #[display(fmt = "git command `{}` failed: {}", _0, _1)]
+    GitCommand(String, String),
+    /// post not found error
+    #[from(ignore)]
+    #[display(fmt = "post {:?} not found", _0)]
+    PostNotFound(PathBuf),
+    /// extra file not found error
+    #[from(ignore)]
+    #[display(fmt = "extra file {:?} not found", _0)]
+    ExtraFileNotFound(PathBuf),
+    /// blog validation failed error
+    #[from(ignore)]
+    #[display(fmt = "blog validation failed: {} problems found", _0)]
+    ValidateFailed(usize),
+    /// blog doctor failed error
+    #[from(ignore)]
+    #[display(fmt = "blog doctor failed: {} checks failed", _0)]
+    DoctorFailed(usize),
+}
+
+impl Error {
+    /// the post or file path the error concerns, if any.
+    pub fn path(&self) -> Option<&Path> {
+        use Error::*;
+        match self {
+            PostHeadPaser(_, path) => Some(path),
+            TagsParse(path, _) => Some(path),
+            FileRead(path, _) => Some(path),
+            FileWrite(path, _) => Some(path),
+            FileCopy(path, _, _) => Some(path),
+            DirCreate(path, _) => Some(path),
+            RootDirExisted(path) => Some(path),
+            PostPathInvaild(path) => Some(path),
+            PostPathExisted(path) => Some(path),
+            ThemeArchiveInvalid(path, _) => Some(path),
+            PostOnlyOnePart(path) => Some(path),
+            PostNoHead(path) => Some(path),
+            PostNoBody(path) => Some(path),
+            PostHeaderUnknown(path, _) => Some(path),
+            PostsEmpty(path) => Some(path),
+            PostSlugDuplicated(_, path) => Some(path),
+            PostLinkBroken(path, _) => Some(path),
+            PostLinkUnresolved(path, _) => Some(path),
+            PostAliasCollision(_, _, path) => Some(path),
+            PostAliasInvalid(_, path) => Some(path),
+            PostAliasOverwrite(_, path, _) => Some(path),
+            PostNotFound(path) => Some(path),
+            ExtraFileNotFound(path) => Some(path),
+            _ => None,
+        }
+    }
 }
 
 impl StdError for Error {
@@ -100,17 +230,62 @@ impl StdError for Error {
             Notify(e) => Some(e),
             GlobPattern(e) => Some(e),
             TomlExport(e) => Some(e),
+            JsonExport(e) => Some(e),
             PathExpend(e) => Some(e),
             PostHeadPaser(e, _) => Some(e),
+            HeadersParse(e) => Some(e),
+            TagsParse(_, e) => Some(e),
+            FileRead(_, e) => Some(e),
+            FileWrite(_, e) => Some(e),
+            FileCopy(_, _, e) => Some(e),
+            DirCreate(_, e) => Some(e),
             RootDirExisted(_) => None,
             PostPathInvaild(_) => None,
             PostPathExisted(_) => None,
             ThemeFileEncoding(_) => None,
             ThemeInUse(_) => None,
             ThemeNotFound(_) => None,
+            ThemeExisted(_) => None,
+            ThemeArchiveInvalid(_, _) => None,
             PostOnlyOnePart(_) => None,
             PostNoHead(_) => None,
             PostNoBody(_) => None,
+            PostHeaderUnknown(_, _) => None,
+            PostsEmpty(_) => None,
+            PostSlugDuplicated(_, _) => None,
+            PostLinkBroken(_, _) => None,
+            PostLinkUnresolved(_, _) => None,
+            TimezoneInvalid(_) => None,
+            PostAliasCollision(_, _, _) => None,
+            PostAliasInvalid(_, _) => None,
+            PostAliasOverwrite(_, _, _) => None,
+            SinceDateInvalid(_) => None,
+            GitCommand(_, _) => None,
+            PostNotFound(_) => None,
+            ExtraFileNotFound(_) => None,
+            ValidateFailed(_) => None,
+            DoctorFailed(_) => None,
         }
     }
 }
+
+/// blog post load error, collected by `Mdblog::load_lenient`
+#[derive(Debug)]
+pub struct PostError {
+    /// post path relative to the blog root directory
+    pub path: PathBuf,
+    /// the post load error
+    pub error: Error,
+}
+
+impl fmt::Display for PostError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.error)
+    }
+}
+
+impl StdError for PostError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(&self.error)
+    }
+}
@@ -1,4 +1,6 @@
 //! static site generator from markdown files.
+//!
+//! the stable public api is re-exported by the `prelude` module.
 
 #![doc(
     html_logo_url = "https://www.rust-lang.org/logos/rust-logo-128x128-blk-v2.png",
@@ -11,38 +13,61 @@
 #![allow(clippy::expect_fun_call)]
 #![allow(clippy::or_fun_call)]
 
-use std::collections::BTreeMap;
-use std::path::{Path, PathBuf};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{self, Write};
+use std::net::TcpStream;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
 use std::rc::Rc;
 use std::sync::mpsc;
 use std::thread;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use config::Config;
+use flate2::read::GzDecoder;
 use glob::Pattern;
-use log::{debug, error, info};
+use log::{debug, error, info, warn};
 use notify::RecursiveMode;
 use notify_debouncer_mini::new_debouncer;
 use tempfile::{Builder as TempBuilder, TempDir};
 use tera::{Context, Tera};
-use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+use time::format_description::{self, well_known::Rfc3339};
+use time::{Date, OffsetDateTime};
 use walkdir::{DirEntry, WalkDir};
 
-pub use crate::error::{Error, Result};
+pub use crate::doctor::{Diagnosis, Doctor};
+pub use crate::error::{Error, PostError, Result};
+pub use crate::manifest::{Manifest, ManifestEntry, MANIFEST_VERSION};
+pub use crate::markdown::{render_markdown, MarkdownRenderer, PulldownCmark};
 pub use crate::page::Page;
 pub use crate::post::Post;
 pub use crate::post::PostHeaders;
-pub use crate::settings::Settings;
-pub use crate::tag::Tag;
+pub use crate::settings::{ExtraFile, Settings};
+pub use crate::stats::Stats;
+pub use crate::tag::{Tag, TagInfo};
 pub use crate::theme::Theme;
-use crate::utils::write_file;
+pub use crate::timing::Timing;
+use crate::utils::{
+    base64_encode, content_hash, escape_html, html_links, mime_type, normalize_trailing_slash, parse_utc_offset,
+    percent_decode, percent_encode_path, read_file, replace_attr_values, sanitize_html, slugify,
+    split_blank_line_headers, split_date_prefix, split_fenced_headers, trim_leading_blank_lines, url_dest,
+    url_scheme_host, write_file,
+};
 
+mod doctor;
 mod error;
+mod manifest;
+mod markdown;
 mod page;
 mod post;
+pub mod prelude;
 mod settings;
+mod stats;
 mod tag;
 mod theme;
+mod timing;
 mod utils;
 
 /// blog object
@@ -55,14 +80,48 @@ pub struct Mdblog {
     theme: Theme,
     /// collection of blog posts
     posts: Vec<Rc<Post>>,
+    /// collection of blog static pages, see `Post::is_page`
+    pages: Vec<Rc<Post>>,
+    /// rendered html of the optional `index.md` intro of index page
+    index_content: String,
+    /// rendered html of the `post_footer` setting
+    post_footer_html: String,
     /// collection of blog index pages
     index_pages: Vec<Rc<Page>>,
     /// collection of blog tags pages
     tag_pages: BTreeMap<String, Vec<Rc<Page>>>,
-    /// tags map
+    /// tags map, keyed by tag slug
     tags_map: BTreeMap<String, Tag>,
     /// server root dir
     server_root_dir: Option<TempDir>,
+    /// build subdirectory of the theme being exported in multiple themes build
+    build_theme: Option<String>,
+    /// the `themes` setting set by `set_build_themes`, kept when the settings are reloaded
+    themes_override: Option<Vec<String>>,
+    /// the `theme_static` setting set by `set_theme_static`, kept when the settings are reloaded
+    theme_static_override: Option<bool>,
+    /// the `jobs` setting set by `set_build_jobs`, kept when the settings are reloaded
+    jobs_override: Option<usize>,
+    /// post markdown renderer
+    renderer: Box<dyn MarkdownRenderer>,
+    /// elapsed time of the last build phases
+    timing: Timing,
+    /// whether hidden(draft) posts are loaded, only enabled by the dev server
+    drafts: bool,
+    /// only render the posts created on or after the date
+    since: Option<Date>,
+    /// whether the posts created in the future are loaded
+    future: bool,
+    /// whether the orphaned output files are deleted after building
+    prune: bool,
+    /// whether the blog is opened with browser once the server is up
+    open: bool,
+    /// the start time of the running build, the output files older than it are orphans
+    build_start: Option<SystemTime>,
+    /// whether building fails if no post is rendered
+    fail_on_empty: bool,
+    /// the theme static files exported by the build by content hash, shared by multiple themes build
+    shared_files: RefCell<BTreeMap<u32, PathBuf>>,
 }
 
 impl Mdblog {
@@ -77,10 +136,27 @@ impl Mdblog {
             settings,
             theme,
             posts: Vec::new(),
+            pages: Vec::new(),
+            index_content: String::new(),
+            post_footer_html: String::new(),
             index_pages: Vec::new(),
             tag_pages: BTreeMap::new(),
             tags_map: BTreeMap::new(),
             server_root_dir: None,
+            build_theme: None,
+            themes_override: None,
+            theme_static_override: None,
+            jobs_override: None,
+            renderer: Box::new(PulldownCmark),
+            timing: Timing::default(),
+            drafts: false,
+            since: None,
+            future: false,
+            prune: false,
+            open: false,
+            build_start: None,
+            fail_on_empty: false,
+            shared_files: RefCell::new(BTreeMap::new()),
         })
     }
 
@@ -91,6 +167,13 @@ impl Mdblog {
     /// * `config.toml`
     /// * `BLOG_` prefix environment variable
     pub fn load_customize_settings(&mut self) -> Result<()> {
+        self.load_settings()?;
+        self.theme = self.load_theme()?;
+        Ok(())
+    }
+
+    /// load blog customize settings without loading the theme.
+    fn load_settings(&mut self) -> Result<()> {
         let settings = Config::builder()
             .add_source(self.settings.clone())
             .add_source(config::File::with_name("config.toml"))
@@ -100,84 +183,548 @@ impl Mdblog {
         if self.settings.site_url.ends_with('/') {
             self.settings.site_url = self.settings.site_url.trim_end_matches('/').to_string();
         }
+        self.settings.index_path = self.settings.index_path.trim_matches('/').to_string();
+        let base_path = self.settings.base_path.trim_matches('/');
+        if !base_path.is_empty() {
+            self.settings.site_url = format!("{}/{}", self.settings.site_url, base_path);
+        }
+        Ok(())
+    }
+
+    /// diagnose common blog setup problems without building or changing anything.
+    ///
+    /// checks the settings, the posts directory, the theme and the posts.
+    pub fn doctor(&mut self) -> Doctor {
+        let mut doctor = Doctor::default();
+
+        let mut config = Diagnosis::new(
+            "config.toml is valid",
+            "fix the toml syntax and setting types of config.toml, or run `mdblog init` to create a blog",
+        );
+        if let Err(e) = self.load_settings() {
+            config.problems.push(e.to_string());
+        }
+        doctor.checks.push(config);
+
+        let mut posts_dir = Diagnosis::new("posts directory exists", "create the `posts` directory of markdown posts");
+        let post_root_dir = self.post_root_dir().map(|dir| dir.is_dir());
+        match post_root_dir {
+            Ok(true) => {}
+            Ok(false) => posts_dir.problems.push(String::from("`posts` directory not found")),
+            Err(e) => posts_dir.problems.push(e.to_string()),
+        }
+        let has_posts_dir = posts_dir.is_ok();
+        doctor.checks.push(posts_dir);
+
+        let mut theme = Diagnosis::new(
+            &format!("theme({}) has the required templates", self.settings.theme),
+            "a theme needs templates/base.tpl, index.tpl and post.tpl, or set `theme = \"simple\"`",
+        );
+        match self.load_theme() {
+            Ok(t) => self.theme = t,
+            Err(e) => theme.problems.push(e.to_string()),
+        }
+        doctor.checks.push(theme);
+
+        if has_posts_dir {
+            let mut posts = Diagnosis::new(
+                "posts are valid",
+                "fence the post headers by `---` lines, or separate them from the body by a blank line",
+            );
+            match self.validate() {
+                Ok(problems) => posts.problems.extend(problems.iter().map(|e| e.to_string())),
+                Err(e) => posts.problems.push(e.to_string()),
+            }
+            doctor.checks.push(posts);
+        }
+        doctor
+    }
+
+    /// load the blog theme of `Settings::theme_dir`, or the `theme` directory of the theme root directory.
+    fn load_theme(&self) -> Result<Theme> {
         let theme_root_dir = self.theme_root_dir()?;
-        self.theme = Theme::new(&theme_root_dir, &self.settings.theme)?;
+        let mut theme = if self.settings.theme_dir.is_empty() {
+            Theme::new(&theme_root_dir, &self.settings.theme)?
+        } else {
+            let theme_dir = get_dir(&self.root, &self.settings.theme_dir)?;
+            Theme::from_dir(&theme_root_dir, &self.settings.theme, theme_dir)?
+        };
+        self.bundle_theme_assets(&mut theme)?;
+        Ok(theme)
+    }
+
+    /// bundle the `theme` static files if `bundle_assets` is enabled.
+    fn bundle_theme_assets(&self, theme: &mut Theme) -> Result<()> {
+        if self.settings.bundle_assets {
+            theme.bundle_assets(&self.settings.bundle_order)?;
+        }
         Ok(())
     }
 
+    /// set the markdown renderer of blog posts, `PulldownCmark` is used by default.
+    pub fn set_markdown_renderer<R: MarkdownRenderer + 'static>(&mut self, renderer: R) {
+        self.renderer = Box::new(renderer);
+    }
+
     fn reset_site_url(&mut self, host: &str, port: u16) {
         self.settings.site_url = format!("http://{}:{}", host, port);
     }
 
-    /// load blog posts.
+    /// load blog posts, stop at the first post load error.
     pub fn load_posts(&mut self) -> Result<()> {
+        self.load_posts_collecting(false)?;
+        Ok(())
+    }
+
+    /// load blog posts without stopping at post load errors.
+    ///
+    /// the bad posts are skipped and their errors are returned all at once.
+    pub fn load_lenient(&mut self) -> Result<Vec<PostError>> {
+        self.load_posts_collecting(true)
+    }
+
+    /// load blog posts, post load errors are collected if `lenient`, otherwise returned.
+    fn load_posts_collecting(&mut self, lenient: bool) -> Result<Vec<PostError>> {
         let mut posts: Vec<Rc<Post>> = Vec::new();
         let mut tags_map: BTreeMap<String, Tag> = BTreeMap::new();
-        let walker = WalkDir::new(&self.post_root_dir()?).into_iter();
+        let mut errors = Vec::new();
+        let now = OffsetDateTime::now_utc();
 
-        for entry in walker.filter_entry(|e| !is_hidden(e)) {
-            let entry = entry.expect("get walker entry error");
-            if !is_markdown_file(&entry) {
+        let mut loaded = Vec::new();
+        for post_path in self.post_paths()? {
+            let post = match self.load_post(&post_path) {
+                Ok(post) => post,
+                Err(error) if lenient => {
+                    errors.push(PostError { path: post_path, error });
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            if post.headers.hidden && !self.drafts {
+                // scheduled posts use a future `created` time, a hidden post is never published by itself
+                if post.headers.created <= now {
+                    warn!(
+                        path:% = post_path.display();
+                        "post {} is hidden but created in the past, it is never published, \
+                         remove `hidden` to publish it or set a future `created` time to schedule it",
+                        post_path.display()
+                    );
+                }
+                debug!(path:% = post_path.display(); "skip hidden post: {}", post_path.display());
                 continue;
             }
-            let post_path = entry.path().strip_prefix(&self.root)?.to_owned();
-            let post = Post::new(&self.root, &post_path)?;
-            let post = Rc::new(post);
-            posts.push(Rc::clone(&post));
-            if post.headers.hidden {
+            if post.headers.created > now && !self.future {
+                debug!(path:% = post_path.display(); "skip future post: {}", post_path.display());
                 continue;
             }
-            for name in &post.headers.tags {
-                let tag = tags_map.entry(name.to_string()).or_insert(Tag::new(name));
-                tag.add(post.clone());
+            loaded.push(post);
+        }
+        self.resolve_post_links(&mut loaded)?;
+
+        let mut pages = Vec::new();
+        for post in loaded {
+            let post = Rc::new(post);
+            if post.is_page() {
+                pages.push(post);
+                continue;
             }
+            posts.push(Rc::clone(&post));
         }
         posts.sort_by(|p1, p2| p2.headers.created.cmp(&p1.headers.created));
+        // the oldest post names the merged tags of the same slug
+        for post in posts.iter().rev().filter(|p| !p.headers.hidden) {
+            add_post_tags(&mut tags_map, post);
+        }
+        let tag_infos = self.load_tag_infos()?;
         for tag in tags_map.values_mut() {
             tag.posts.sort_by(|p1, p2| p2.headers.created.cmp(&p1.headers.created));
+            if let Some(info) = tag_infos.get(&tag.name) {
+                tag.description.clone_from(&info.description);
+            }
         }
+        pages.sort_by(|p1, p2| p1.path.cmp(&p2.path));
         self.posts = posts;
+        self.pages = pages;
         self.tags_map = tags_map;
+        Ok(errors)
+    }
+
+    /// the `post:<slug>` link targets, the slug of a post is the slug of its file stem without date prefix,
+    /// `posts/my-post/index.md` is named after its directory `my-post`.
+    fn post_link_targets(&self, posts: &[Post]) -> BTreeMap<String, String> {
+        let mut targets = BTreeMap::new();
+        for post in posts {
+            let name = post
+                .path
+                .file_stem()
+                .filter(|stem| *stem != "index")
+                .or_else(|| post.path.parent().and_then(|dir| dir.file_name()))
+                .unwrap_or_default()
+                .to_string_lossy();
+            let slug = slugify(split_date_prefix(&name).map_or(&name, |(_, rest)| rest));
+            let url = format!("{}{}", self.settings.site_url, percent_encode_path(&post.formatted_path));
+            targets.entry(slug).or_insert(url);
+        }
+        targets
+    }
+
+    /// resolve the `post:<slug>` links of `posts` to the urls of the target posts.
+    ///
+    /// links matching no post are kept and warned, or returned as error if `Settings::strict_post_links`.
+    fn resolve_post_links(&self, posts: &mut [Post]) -> Result<()> {
+        let targets = self.post_link_targets(posts);
+        for post in posts.iter_mut() {
+            let mut unresolved = BTreeSet::new();
+            let mut resolve = |link: &str| {
+                let target = link.strip_prefix("post:")?;
+                let (slug, fragment) = match target.find('#') {
+                    Some(i) => target.split_at(i),
+                    None => (target, ""),
+                };
+                match targets.get(slug) {
+                    Some(url) => Some(format!("{}{}", url, fragment)),
+                    None => {
+                        unresolved.insert(link.to_string());
+                        None
+                    }
+                }
+            };
+            post.content = replace_attr_values(&post.content, "href", &mut resolve);
+            post.excerpt = replace_attr_values(&post.excerpt, "href", &mut resolve);
+            for link in unresolved {
+                if self.settings.strict_post_links {
+                    return Err(Error::PostLinkUnresolved(post.path.clone(), link));
+                }
+                warn!(path:% = post.path.display(); "post {} link {:?} matches no post", post.path.display(), link);
+            }
+        }
         Ok(())
     }
 
+    /// load the optional `index.md` of blog root directory, rendered above the post list of the first index page.
+    ///
+    /// the file is a markdown body without headers.
+    fn load_index_content(&mut self) -> Result<()> {
+        let path = self.root.join("index.md");
+        if !path.is_file() {
+            self.index_content = String::new();
+            return Ok(());
+        }
+        let mut buf = Vec::new();
+        read_file(&path, &mut buf)?;
+        self.index_content = self.render_markdown(&String::from_utf8_lossy(&buf));
+        Ok(())
+    }
+
+    /// render the `post_footer` setting once for all posts.
+    fn load_post_footer(&mut self) {
+        self.post_footer_html = if self.settings.post_footer.is_empty() {
+            String::new()
+        } else {
+            self.render_markdown(&self.settings.post_footer)
+        };
+    }
+
+    /// render the markdown `text` out of posts, sanitized if `sanitize_html` is enabled.
+    fn render_markdown(&self, text: &str) -> String {
+        let html = self.renderer.render(text, &self.settings);
+        if self.settings.sanitize_html {
+            sanitize_html(&html)
+        } else {
+            html
+        }
+    }
+
+    /// load tag metadata from the optional `tags.toml` file of blog root directory.
+    fn load_tag_infos(&self) -> Result<BTreeMap<String, TagInfo>> {
+        let path = self.root.join("tags.toml");
+        if !path.is_file() {
+            return Ok(BTreeMap::new());
+        }
+        let mut buf = Vec::new();
+        read_file(&path, &mut buf)?;
+        let content = String::from_utf8_lossy(&buf);
+        toml::from_str(&content).map_err(|e| Error::TagsParse(path, e))
+    }
+
+    /// load blog posts and collect content statistics.
+    pub fn stats(&mut self) -> Result<Stats> {
+        self.load_posts()?;
+        let posts = self.posts.len();
+        let words: usize = self.posts.iter().map(|p| p.word_count).sum();
+        let mut tags: Vec<(String, usize)> = self.tags_map.values().map(|t| (t.name.clone(), t.posts.len())).collect();
+        tags.sort_by(|t1, t2| t2.1.cmp(&t1.1).then_with(|| t1.0.cmp(&t2.0)));
+        let first_created = match self.posts.last() {
+            Some(post) => Some(post.headers.created.format(&Rfc3339)?),
+            None => None,
+        };
+        let last_created = match self.posts.first() {
+            Some(post) => Some(post.headers.created.format(&Rfc3339)?),
+            None => None,
+        };
+        Ok(Stats {
+            posts,
+            words,
+            average_words: if posts == 0 { 0 } else { words / posts },
+            tags,
+            first_created,
+            last_created,
+        })
+    }
+
+    /// rewrite the blank line separated headers of blog posts to be fenced by `---` lines.
+    ///
+    /// every migrated post is backed up as `<post>.bak`, nothing is written if `dry_run`.
+    pub fn migrate(&self, dry_run: bool) -> Result<Vec<PathBuf>> {
+        let mut migrated = Vec::new();
+        for post_path in self.post_paths()? {
+            let path = self.root.join(&post_path);
+            let mut buf = Vec::new();
+            read_file(&path, &mut buf)?;
+            let content = match std::str::from_utf8(&buf) {
+                Ok(content) => trim_leading_blank_lines(content),
+                Err(e) => return Err(Error::FileRead(path, io::Error::new(io::ErrorKind::InvalidData, e))),
+            };
+            if split_fenced_headers(content).is_some() {
+                continue;
+            }
+            let nl = if content.contains("\r\n") { "\r\n" } else { "\n" };
+            let fenced = match split_blank_line_headers(content) {
+                Some((head, body)) => format!("---{0}{1}{0}---{0}{0}{2}", nl, head, body),
+                None => format!("---{0}{1}{0}---{0}", nl, content.trim_end()),
+            };
+            if dry_run {
+                info!(path:% = post_path.display(); "would migrate post: {}", post_path.display());
+            } else {
+                let mut backup = path.clone().into_os_string();
+                backup.push(".bak");
+                write_file(Path::new(&backup), &buf)?;
+                write_file(&path, fenced.as_bytes())?;
+                info!(path:% = post_path.display(); "migrated post: {}", post_path.display());
+            }
+            migrated.push(post_path);
+        }
+        Ok(migrated)
+    }
+
+    /// blog post and static page paths relative to the blog root directory.
+    fn post_paths(&self) -> Result<Vec<PathBuf>> {
+        let mut paths = Vec::new();
+        let page_root_dir = self.page_root_dir()?;
+        let mut dirs = vec![self.post_root_dir()?];
+        if page_root_dir.exists() {
+            dirs.push(page_root_dir);
+        }
+        for dir in &dirs {
+            let walker = WalkDir::new(dir).into_iter();
+            for entry in walker.filter_entry(|e| !is_hidden(e)) {
+                let entry = entry.expect("get walker entry error");
+                let is_html_post = is_html_file(&entry) && has_front_matter(entry.path());
+                if !is_html_post && !is_markdown_file(&entry, &self.settings.markdown_extensions) {
+                    continue;
+                }
+                paths.push(entry.path().strip_prefix(&self.root)?.to_owned());
+            }
+        }
+        Ok(paths)
+    }
+
+    /// hand-written `.html` files without front matter in the posts and pages directories,
+    /// relative to the blog root directory, they are copied into the build directory verbatim.
+    fn passthrough_paths(&self) -> Result<Vec<PathBuf>> {
+        let mut paths = Vec::new();
+        for dir in &[self.post_root_dir()?, self.page_root_dir()?] {
+            if !dir.exists() {
+                continue;
+            }
+            let walker = WalkDir::new(dir).into_iter();
+            for entry in walker.filter_entry(|e| !is_hidden(e)) {
+                let entry = entry.expect("get walker entry error");
+                if is_html_file(&entry) && !has_front_matter(entry.path()) {
+                    paths.push(entry.path().strip_prefix(&self.root)?.to_owned());
+                }
+            }
+        }
+        Ok(paths)
+    }
+
+    /// load the blog post of `post_path`.
+    fn load_post(&self, post_path: &Path) -> Result<Post> {
+        let mut post = Post::with_settings(self.root.as_path(), post_path, &self.settings, self.renderer.as_ref())?;
+        post.normalize_url(&self.settings.trailing_slash);
+        Ok(post)
+    }
+
+    /// validate blog posts without building.
+    ///
+    /// checks post format and headers, duplicate post slugs and broken internal links,
+    /// every problem found is returned instead of stopping at the first one.
+    pub fn validate(&self) -> Result<Vec<Error>> {
+        let mut problems = Vec::new();
+        let mut posts = Vec::new();
+        for post_path in self.post_paths()? {
+            match self.load_post(&post_path) {
+                Ok(post) => posts.push(post),
+                Err(e) => problems.push(e),
+            }
+        }
+
+        // e.g. `posts/2021-01-01-hello.md` and `posts/hello.md` are both written to `posts/hello.html`
+        let mut dests: BTreeMap<PathBuf, &Post> = BTreeMap::new();
+        for post in &posts {
+            if let Some(other) = dests.insert(post.dest(), post) {
+                problems.push(Error::PostSlugDuplicated(other.path.clone(), post.path.clone()));
+            }
+        }
+
+        let urls = self.internal_urls(&posts)?;
+        let targets = self.post_link_targets(&posts);
+        for post in &posts {
+            for link in html_links(&post.content) {
+                if let Some(slug) = link.strip_prefix("post:") {
+                    if !targets.contains_key(slug.split('#').next().unwrap_or("")) {
+                        problems.push(Error::PostLinkUnresolved(post.path.clone(), link));
+                    }
+                    continue;
+                }
+                if let Some(url) = resolve_link(&post.formatted_path, &link, &self.settings.site_url) {
+                    if !urls.contains(&url) {
+                        problems.push(Error::PostLinkBroken(post.path.clone(), link));
+                    }
+                }
+            }
+        }
+        Ok(problems)
+    }
+
+    /// the urls of blog generated files, relative to the site root.
+    fn internal_urls(&self, posts: &[Post]) -> Result<BTreeSet<String>> {
+        let mut urls: BTreeSet<String> = posts.iter().map(|p| p.formatted_path.clone()).collect();
+        let total = posts.iter().filter(|p| !p.headers.hidden && !p.is_page()).count();
+        let n = page_ranges(total, self.settings.posts_per_page).len();
+        urls.insert(String::from("/"));
+        urls.insert(self.index_page_url("index.html"));
+        for i in 1..=n {
+            urls.insert(self.index_page_url(&format_page_name("index", i, n)));
+        }
+        urls.insert(self.page_url("/tags.html"));
+        urls.insert(String::from("/atom.xml"));
+        if self.settings.latest_page && total > 0 {
+            urls.insert(String::from("/latest/"));
+            urls.insert(String::from("/latest/index.html"));
+        }
+        let mut tag_nums: BTreeMap<String, usize> = BTreeMap::new();
+        for post in posts.iter().filter(|p| !p.headers.hidden && !p.is_page()) {
+            let slugs: BTreeSet<_> = post.headers.tags.iter().map(|name| slugify(name)).collect();
+            for slug in slugs {
+                *tag_nums.entry(slug).or_insert(0) += 1;
+            }
+        }
+        let per_tag_page = self.posts_per_tag_page();
+        for (slug, num) in tag_nums {
+            for i in 1..=page_ranges(num, per_tag_page).len() {
+                urls.insert(self.page_url(&format!("/tags/{}", format_tag_page_name(&slug, i))));
+            }
+        }
+        // both the normalized links and the paths of the written files are valid link targets
+        let dests: Vec<_> = urls
+            .iter()
+            .map(|url| format!("/{}", url_dest(url).to_string_lossy().replace('\\', "/")))
+            .collect();
+        urls.extend(dests);
+        for dest in self.theme.static_manifest(&self.settings.cache_bust, &self.settings.static_dir).values() {
+            urls.insert(format!("/{}", dest.split('?').next().unwrap_or(dest)));
+        }
+        let media_root_dir = self.media_root_dir()?;
+        if media_root_dir.exists() {
+            let walker = WalkDir::new(&media_root_dir).into_iter();
+            for entry in walker.filter_entry(|e| !is_hidden(e)) {
+                let entry = entry.expect("get walker entry error");
+                let rel_path = entry.path().strip_prefix(&media_root_dir)?;
+                urls.insert(format!("/media/{}", rel_path.to_string_lossy().replace('\\', "/")));
+            }
+        }
+        Ok(urls)
+    }
+
+    /// the link of the generated page `path`, normalized by the `trailing_slash` setting.
+    fn page_url(&self, path: &str) -> String {
+        normalize_trailing_slash(path, &self.settings.trailing_slash)
+    }
+
+    /// the link of the index page file `name`, under the `index_path` setting directory.
+    fn index_page_url(&self, name: &str) -> String {
+        if self.settings.index_path.is_empty() {
+            self.page_url(&format!("/{}", name))
+        } else {
+            self.page_url(&format!("/{}/{}", self.settings.index_path, name))
+        }
+    }
+
     /// build index pages
+    ///
+    /// featured posts are put in front of the others, both in `sort_order` setting order.
     pub fn build_index_pages(&mut self) -> Result<()> {
-        let posts: Vec<_> = self.posts.iter().filter(|p| !p.headers.hidden).collect();
-        let total = posts.len();
-        let n = (total + self.settings.posts_per_page - 1) / self.settings.posts_per_page;
-        let mut i = 1;
-        while i <= n {
-            let start = (i - 1) * self.settings.posts_per_page;
-            let end = total.min(start + self.settings.posts_per_page);
+        self.index_pages.clear();
+        let (mut featured, mut others): (Vec<_>, Vec<_>) = self
+            .posts
+            .iter()
+            .filter(|p| !p.headers.hidden)
+            .partition(|p| p.headers.featured);
+        sort_posts(&mut featured, &self.settings.sort_order);
+        sort_posts(&mut others, &self.settings.sort_order);
+        let posts: Vec<_> = featured.into_iter().chain(others).collect();
+        let ranges = page_ranges(posts.len(), self.settings.posts_per_page);
+        let total = ranges.len();
+        for (i, (start, end)) in ranges.into_iter().enumerate() {
+            let name = format_page_name("index", i + 1, total);
             let page = Page {
-                index: i,
-                name: format_page_name("index", i, total),
-                posts: posts[start..end].to_vec().into_iter().map(|p| p.to_owned()).collect(),
+                index: i + 1,
+                url: self.index_page_url(&name),
+                name,
+                posts: posts[start..end].iter().map(|p| Rc::clone(p)).collect(),
             };
             self.index_pages.push(Rc::new(page));
-            i += 1;
         }
         Ok(())
     }
 
+    /// the number of posts per tag page, see `Settings::posts_per_tag_page`.
+    fn posts_per_tag_page(&self) -> usize {
+        match self.settings.posts_per_tag_page {
+            0 => self.settings.posts_per_page,
+            n => n,
+        }
+    }
+
     /// build tag pages
+    ///
+    /// tag posts are in `sort_order` setting order,
+    /// the first page is `tags/<slug>.html`, the others are `tags/<slug>/page/<n>/index.html`.
     pub fn build_tag_pages(&mut self) -> Result<()> {
+        self.tag_pages.clear();
+        let per_page = self.posts_per_tag_page();
+        let urls: Vec<_> = self
+            .tags_map
+            .values()
+            .map(|tag| self.page_url(&format!("/tags/{}", format_tag_page_name(&tag.slug, 1))))
+            .collect();
+        for (tag, url) in self.tags_map.values_mut().zip(urls) {
+            tag.url = url;
+        }
         for tag in self.tags_map.values() {
-            let total = tag.posts.len();
-            let n = (total + self.settings.posts_per_page - 1) / self.settings.posts_per_page;
-            let mut i = 1;
-            while i <= n {
-                let start = (i - 1) * self.settings.posts_per_page;
-                let end = total.min(start + self.settings.posts_per_page);
+            let mut posts: Vec<_> = tag.posts.iter().collect();
+            sort_posts(&mut posts, &self.settings.sort_order);
+            for (i, (start, end)) in page_ranges(posts.len(), per_page).into_iter().enumerate() {
+                let name = format_tag_page_name(&tag.slug, i + 1);
                 let page = Page {
-                    index: i,
-                    name: format_page_name(&tag.name, i, total),
-                    posts: tag.posts[start..end].to_vec().into_iter().collect(),
+                    index: i + 1,
+                    url: self.page_url(&format!("/tags/{}", name)),
+                    name,
+                    posts: posts[start..end].iter().map(|p| Rc::clone(p)).collect(),
                 };
-                let pages = self.tag_pages.entry(tag.name.clone()).or_insert(Vec::new());
+                let pages = self.tag_pages.entry(tag.name.clone()).or_default();
                 pages.push(Rc::new(page));
-                i += 1;
             }
         }
         Ok(())
@@ -193,7 +740,7 @@ impl Mdblog {
         tera.add_raw_template("hello.md.tpl", include_str!("demo/hello.md.tpl"))?;
         tera.add_raw_template("math.md.tpl", include_str!("demo/math.md.tpl"))?;
 
-        let now = OffsetDateTime::now_local()?;
+        let now = self.now()?;
         let mut context = Context::new();
         context.insert("now", &now.format(&Rfc3339)?);
 
@@ -203,44 +750,337 @@ impl Mdblog {
         write_file(&self.post_root_dir()?.join("math.md"), math_content.as_bytes())?;
 
         self.export_config()?;
+        self.export_gitignore()?;
 
         self.theme.init_dir(&self.theme.name)?;
-        std::fs::create_dir_all(self.root.join("media"))?;
+        let media_dir = self.root.join("media");
+        std::fs::create_dir_all(&media_dir).map_err(|e| Error::DirCreate(media_dir, e))?;
         Ok(())
     }
 
     /// build the blog html files to `build_dir` directory.
+    ///
+    /// if `themes` setting is not empty, every theme is exported into `build_dir/<theme>` subdirectory.
     pub fn build(&mut self) -> Result<()> {
+        self.timing = Timing::default();
+        self.shared_files.borrow_mut().clear();
+        // coarse file system timestamps may round the modified time down
+        let build_start = SystemTime::now() - Duration::from_secs(1);
+        self.build_start = Some(build_start);
+        let start = Instant::now();
         self.load_posts()?;
+        if self.fail_on_empty {
+            let since = self.since;
+            let rendered = self.posts.iter().filter(|p| since.map_or(true, |d| p.headers.created.date() >= d));
+            if rendered.count() == 0 {
+                return Err(Error::PostsEmpty(self.post_root_dir()?));
+            }
+        }
+        self.load_index_content()?;
+        self.load_post_footer();
         self.build_index_pages()?;
         self.build_tag_pages()?;
+        self.timing.load = start.elapsed();
+        if self.settings.canonical_host_check {
+            self.check_canonical_host();
+        }
+        if self.settings.themes.is_empty() {
+            self.export()?;
+        } else {
+            let theme_root_dir = self.theme_root_dir()?;
+            let site_url = self.settings.site_url.clone();
+            for name in self.settings.themes.clone() {
+                info!("exporting theme({}) build ...", name);
+                let mut theme = Theme::new(&theme_root_dir, &name)?;
+                self.bundle_theme_assets(&mut theme)?;
+                self.theme = theme;
+                self.settings.site_url = format!("{}/{}", site_url, name);
+                self.build_theme = Some(name);
+                self.export()?;
+            }
+            self.build_theme = None;
+            self.settings.site_url = site_url;
+            self.theme = self.load_theme()?;
+        }
+        if self.server_root_dir.is_none() {
+            self.handle_orphans(build_start)?;
+        }
+        self.build_start = None;
+        Ok(())
+    }
+
+    /// the orphaned output files deleted at the end of the running build, see `orphans`.
+    ///
+    /// they are left out of `manifest.json` and `_headers`, which are exported before.
+    fn pruned_orphans(&self) -> Result<BTreeSet<PathBuf>> {
+        match self.build_start {
+            Some(build_start) if self.prune && self.server_root_dir.is_none() => {
+                Ok(self.orphans(build_start)?.into_iter().collect())
+            }
+            _ => Ok(BTreeSet::new()),
+        }
+    }
+
+    /// the output files not written since `build_start`, left by renamed or deleted posts.
+    ///
+    /// hidden files like `.git` and the posts skipped by the `since` date are not orphans.
+    pub fn orphans(&self, build_start: SystemTime) -> Result<Vec<PathBuf>> {
+        let build_dir = self.build_root_dir()?;
+        let mut known = BTreeSet::new();
+        if self.since.is_some() {
+            let roots: Vec<PathBuf> = if self.settings.themes.is_empty() {
+                vec![build_dir.clone()]
+            } else {
+                self.settings.themes.iter().map(|name| build_dir.join(name)).collect()
+            };
+            for root in &roots {
+                for post in &self.posts {
+                    known.insert(root.join(post.dest()));
+                }
+            }
+        }
+        let mut orphans = Vec::new();
+        let walker = WalkDir::new(&build_dir).min_depth(1).into_iter();
+        for entry in walker.filter_entry(|e| !is_hidden(e)) {
+            let entry = entry.expect("get walker entry error");
+            if !entry.file_type().is_file() || known.contains(entry.path()) {
+                continue;
+            }
+            let modified = entry.metadata().map_err(io::Error::from)?.modified()?;
+            if modified < build_start {
+                orphans.push(entry.into_path());
+            }
+        }
+        Ok(orphans)
+    }
+
+    /// warn about the orphaned output files, or delete them if pruning is enabled.
+    fn handle_orphans(&self, build_start: SystemTime) -> Result<()> {
+        let build_dir = self.build_root_dir()?;
+        for path in self.orphans(build_start)? {
+            if !self.prune {
+                warn!(path:% = path.display(); "orphaned output file: {}, delete it with `--prune`", path.display());
+                continue;
+            }
+            info!(path:% = path.display(); "pruning orphaned output file: {}", path.display());
+            std::fs::remove_file(&path)?;
+            // remove the directories left empty, stop at the first non-empty one
+            for dir in path.ancestors().skip(1).take_while(|d| *d != build_dir) {
+                if std::fs::remove_dir(dir).is_err() {
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// build a single maintenance page into `index.html` from the theme `maintenance.tpl`, posts are ignored.
+    ///
+    /// it is a placeholder site deployed before the blog content is ready, replaced by a later normal build.
+    pub fn build_maintenance(&mut self) -> Result<()> {
+        info!("maintenance build: only the maintenance page is built, posts are ignored");
+        let build_dir = self.build_root_dir()?;
+        let mut context = self.get_base_context()?;
+        context.insert("page_type", "maintenance");
+        context.insert("page_title", &self.page_title(&self.settings.site_name));
+        let html = self.theme.renderer.render("maintenance.tpl", &context)?;
+        let dest = build_dir.join("index.html");
+        write_file(&dest, html.as_bytes())?;
+        info!(path:% = dest.display(); "maintenance page: {}", dest.display());
+        Ok(())
+    }
+
+    /// warn about post links to the `site_url` host with a different scheme or `www.` prefix.
+    fn check_canonical_host(&self) {
+        let (scheme, host) = match url_scheme_host(&self.settings.site_url) {
+            Some(scheme_host) => scheme_host,
+            None => return,
+        };
+        let bare_host = host.trim_start_matches("www.");
+        for post in &self.posts {
+            for link in html_links(&post.content) {
+                if let Some((link_scheme, link_host)) = url_scheme_host(&link) {
+                    if !link_host.trim_start_matches("www.").eq_ignore_ascii_case(bare_host) {
+                        continue;
+                    }
+                    if !link_scheme.eq_ignore_ascii_case(scheme) || !link_host.eq_ignore_ascii_case(host) {
+                        warn!(
+                            path:% = post.path.display();
+                            "post {} links to non-canonical host {}, consider making it relative",
+                            post.path.display(),
+                            link
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// build the blog into `dir`, e.g. a `gh-pages` branch worktree, and commit it with git if `commit`.
+    pub fn deploy(&mut self, dir: &Path, commit: bool) -> Result<()> {
+        self.settings.build_dir = dir.to_string_lossy().into_owned();
+        self.build()?;
+        let build_dir = self.build_root_dir()?;
+        info!(path:% = build_dir.display(); "deployed blog into {}", build_dir.display());
+        if !commit {
+            return Ok(());
+        }
+        if run_git(&build_dir, &["status", "--porcelain"])?.trim().is_empty() {
+            info!("nothing to commit");
+            return Ok(());
+        }
+        run_git(&build_dir, &["add", "--all"])?;
+        let message = format!("Deploy blog at {}", self.now()?.format(&Rfc3339)?);
+        run_git(&build_dir, &["commit", "--quiet", "-m", &message])?;
+        info!("committed: {}", message);
+        Ok(())
+    }
+
+    /// set the themes of multiple themes build.
+    pub fn set_build_themes(&mut self, themes: Vec<String>) {
+        self.themes_override = Some(themes);
+        self.apply_overrides();
+    }
+
+    /// apply the settings set by the `set_*` methods over the settings loaded from `config.toml`.
+    fn apply_overrides(&mut self) {
+        if let Some(ref themes) = self.themes_override {
+            self.settings.themes.clone_from(themes);
+        }
+        if let Some(theme_static) = self.theme_static_override {
+            self.settings.theme_static = theme_static;
+        }
+        if let Some(jobs) = self.jobs_override {
+            self.settings.jobs = jobs;
+        }
+    }
+
+    /// set whether theme static files are exported.
+    pub fn set_theme_static(&mut self, theme_static: bool) {
+        self.theme_static_override = Some(theme_static);
+        self.apply_overrides();
+    }
+
+    /// set whether hidden(draft) posts are built, never enable it for a deployed build.
+    pub fn set_drafts(&mut self, drafts: bool) {
+        self.drafts = drafts;
+    }
+
+    /// set whether the posts created in the future are built, they are skipped by default.
+    pub fn set_future(&mut self, future: bool) {
+        self.future = future;
+    }
+
+    /// set whether the blog is opened with browser once the server is up, see `serve`.
+    pub fn set_open(&mut self, open: bool) {
+        self.open = open;
+    }
+
+    /// set whether the orphaned output files are deleted after building, they are only warned by default.
+    pub fn set_prune(&mut self, prune: bool) {
+        self.prune = prune;
+    }
+
+    /// set whether building fails if no post is rendered, e.g. to guard against deploying a blank site in CI.
+    pub fn set_fail_on_empty(&mut self, fail_on_empty: bool) {
+        self.fail_on_empty = fail_on_empty;
+    }
+
+    /// set the `YYYY-MM-DD` date, only the posts created on or after it are rendered,
+    /// all posts are still loaded for index and tag pages.
+    pub fn set_build_since(&mut self, since: &str) -> Result<()> {
+        let format = format_description::parse("[year]-[month]-[day]").expect("date format description error");
+        let date = Date::parse(since.trim(), &format).map_err(|_| Error::SinceDateInvalid(since.into()))?;
+        self.since = Some(date);
+        Ok(())
+    }
+
+    /// set the number of parallel build jobs.
+    pub fn set_build_jobs(&mut self, jobs: usize) {
+        self.jobs_override = Some(jobs);
+        self.apply_overrides();
+    }
+
+    /// the number of parallel build jobs, see `Settings::jobs`.
+    fn build_jobs(&self) -> usize {
+        match self.settings.jobs {
+            0 => thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+            n => n,
+        }
+    }
+
+    /// export the blog html files with the current theme.
+    fn export(&mut self) -> Result<()> {
+        let mut timing = self.timing;
+
+        let start = Instant::now();
         self.export_media()?;
         self.export_static()?;
+        timing.static_files += start.elapsed();
+
+        let start = Instant::now();
         self.export_posts()?;
+        self.export_pages()?;
+        self.export_passthrough()?;
+        timing.posts += start.elapsed();
+
+        let start = Instant::now();
         self.export_index()?;
         self.export_tags()?;
-        for tag in self.tags_map.values() {
-            self.export_tag(tag)?;
+        if self.theme.has_template("tag.tpl") {
+            for tag in self.tags_map.values() {
+                self.export_tag(tag)?;
+            }
+        } else {
+            warn!("theme({}) has no tag.tpl, skip exporting tag pages", self.theme.name);
         }
+        timing.pages += start.elapsed();
+
+        let start = Instant::now();
         self.export_atom()?;
+        self.export_aliases()?;
+        self.export_latest()?;
+        timing.feeds += start.elapsed();
+
+        let start = Instant::now();
+        self.export_extra_files()?;
+        // `_headers` goes first to be listed by the manifest
+        self.export_headers_file()?;
+        self.export_manifest()?;
+        timing.static_files += start.elapsed();
+
+        self.timing = timing;
         Ok(())
     }
 
-    /// serve the blog static files in the `build_dir` directory.
+    /// the elapsed time of the last build phases, accumulated over all themes of multiple themes build.
+    pub fn timing(&self) -> &Timing {
+        &self.timing
+    }
+
+    /// serve the blog static files in a temporary directory, rebuild on change.
+    ///
+    /// the blog is opened with browser once the server is up if `set_open` is enabled.
     pub fn serve(&mut self, host: String, port: u16) -> Result<()> {
-        let addr_str = format!("{}:{}", host, port);
-        let server_root_dir = TempBuilder::new().prefix("mdblog.").rand_bytes(10).tempdir()?;
-        info!("server root dir: {}", &server_root_dir.path().display());
+        self.build_and_serve(host, port, true)
+    }
 
+    /// build the blog static files in a temporary directory and serve them.
+    ///
+    /// the build directory is left alone, as the served files link to the local server `site_url`.
+    /// if `watch` is true, rebuild blog when some files modified.
+    /// the blog is opened with browser once the server is up if `set_open` is enabled.
+    pub fn build_and_serve(&mut self, host: String, port: u16, watch: bool) -> Result<()> {
+        let server_root_dir = TempBuilder::new().prefix("mdblog.").rand_bytes(10).tempdir()?;
+        info!(path:% = server_root_dir.path().display(); "server root dir: {}", &server_root_dir.path().display());
         self.server_root_dir = Some(server_root_dir);
-        self.settings.site_url = format!("http://{}", &addr_str);
+        self.reset_site_url(&host, port);
         self.build()?;
 
         info!("server blog at {}", &self.settings.site_url);
-        let server_root_dir = self.server_root_dir.as_ref().unwrap().path().to_owned();
-
-        self.reset_site_url(&host, port);
-        thread::spawn(move || {
+        let server_root_dir = self.build_root_dir()?;
+        let addr = format!("{}:{}", host, port);
+        let server = thread::spawn(move || {
             let mut config = rocket::config::Config::production();
             config
                 .set_address(&host)
@@ -251,8 +1091,14 @@ impl Mdblog {
                 .launch();
         });
 
-        self.open_browser();
-        self.watch()?;
+        if self.open {
+            self.open_browser(addr);
+        }
+        if watch {
+            self.watch()?;
+        } else if server.join().is_err() {
+            error!("blog server stopped unexpectedly");
+        }
         Ok(())
     }
 
@@ -260,10 +1106,15 @@ impl Mdblog {
     fn watch(&mut self) -> Result<()> {
         let (tx, rx) = mpsc::channel();
         let ignore_patterns = self.ignore_patterns()?;
-        info!("watching dir: {}", self.root.display());
+        info!(path:% = self.root.display(); "watching dir: {}", self.root.display());
 
         let mut debouncer = new_debouncer(Duration::from_secs(2), tx)?;
         debouncer.watcher().watch(&self.root, RecursiveMode::Recursive)?;
+        // a `theme_dir` outside of the blog root is watched too
+        if self.theme.dir.is_dir() && !self.theme.dir.starts_with(&self.root) {
+            info!(path:% = self.theme.dir.display(); "watching theme dir: {}", self.theme.dir.display());
+            debouncer.watcher().watch(&self.theme.dir, RecursiveMode::Recursive)?;
+        }
 
         let interval = Duration::new(self.settings.rebuild_interval.into(), 0);
         let mut last_run: Option<Instant> = None;
@@ -281,8 +1132,8 @@ impl Mdblog {
                         }
                     }
                     last_run = Some(now);
-                    info!("Modified file: {}", event.path.display());
-                    if let Err(ref e) = self.rebuild() {
+                    info!(path:% = event.path.display(); "Modified file: {}", event.path.display());
+                    if let Err(ref e) = self.rebuild_modified(&event.path) {
                         crate::utils::log_error_chain(e);
                         return;
                     }
@@ -293,11 +1144,20 @@ impl Mdblog {
         Ok(())
     }
 
-    /// open url with browser
-    fn open_browser(&self) {
+    /// open url with browser after the server at `addr` accepts connections.
+    fn open_browser(&self, addr: String) {
         let url = self.settings.site_url.clone();
         thread::spawn(move || {
-            open::that(url).unwrap();
+            for _ in 0..50 {
+                if TcpStream::connect(&addr).is_ok() {
+                    if let Err(e) = open::that(&url) {
+                        error!("can not open browser at {}: {}", url, e);
+                    }
+                    return;
+                }
+                thread::sleep(Duration::from_millis(100));
+            }
+            error!("server at {} is not up, do not open browser", addr);
         });
     }
 
@@ -306,18 +1166,52 @@ impl Mdblog {
         info!("Rebuild blog again...");
         let site_url = self.settings.site_url.clone();
         self.load_customize_settings()?;
+        self.apply_overrides();
         self.settings.site_url = site_url;
         self.build()?;
         info!("Rebuild done!");
         Ok(())
     }
 
+    /// rebuild blog for the modified file of `path`.
+    ///
+    /// if `path` is a file of the current theme, only the theme is reloaded and the loaded posts are exported again,
+    /// otherwise the whole blog is rebuilt.
+    fn rebuild_modified(&mut self, path: &Path) -> Result<()> {
+        if !self.settings.themes.is_empty() || !path.starts_with(&self.theme.dir) {
+            return self.rebuild();
+        }
+        info!("Reload theme({}) ...", self.theme.name);
+        self.theme.reload()?;
+        if self.settings.bundle_assets {
+            self.theme.bundle_assets(&self.settings.bundle_order)?;
+        }
+        self.export()?;
+        info!("Rebuild done!");
+        Ok(())
+    }
+
+    /// the current time in the `timezone` setting, the system local offset is used if not set.
+    pub fn now(&self) -> Result<OffsetDateTime> {
+        match self.settings.timezone.as_str() {
+            "" | "local" => Ok(OffsetDateTime::now_local()?),
+            tz => {
+                let offset = parse_utc_offset(tz).ok_or_else(|| Error::TimezoneInvalid(tz.into()))?;
+                Ok(OffsetDateTime::now_utc().to_offset(offset))
+            }
+        }
+    }
+
     /// blog build directory absolute path.
     pub fn build_root_dir(&self) -> Result<PathBuf> {
-        if let Some(ref server_root_dir) = self.server_root_dir {
-            Ok(server_root_dir.path().to_owned())
+        let build_dir = if let Some(ref server_root_dir) = self.server_root_dir {
+            server_root_dir.path().to_owned()
         } else {
-            get_dir(&self.root, &self.settings.build_dir)
+            get_dir(&self.root, &self.settings.build_dir)?
+        };
+        match self.build_theme {
+            Some(ref name) => Ok(build_dir.join(name)),
+            None => Ok(build_dir),
         }
     }
 
@@ -336,6 +1230,11 @@ impl Mdblog {
         Ok(self.root.join("posts"))
     }
 
+    /// blog static pages root directory, it is optional.
+    pub fn page_root_dir(&self) -> Result<PathBuf> {
+        Ok(self.root.join("pages"))
+    }
+
     /// blog glob ignore patterns.
     ///
     /// the patterns are used when :
@@ -352,7 +1251,12 @@ impl Mdblog {
         Ok(patterns)
     }
 
-    /// create a new sample post.
+    /// the markdown file path of the new post `path`, see `create_post`.
+    pub fn post_file_path(&self, path: &Path) -> Result<PathBuf> {
+        Ok(self.post_root_dir()?.join(path).with_extension("md"))
+    }
+
+    /// create a new sample post, its file path is `post_file_path`.
     pub fn create_post(&self, path: &Path, tags: &[String]) -> Result<()> {
         let post_title = path.file_stem();
         if !path.is_relative()
@@ -366,11 +1270,11 @@ impl Mdblog {
         if path.is_dir() {
             return Err(Error::PostPathExisted(path.into()));
         }
-        let post_path = self.post_root_dir()?.join(path).with_extension("md");
+        let post_path = self.post_file_path(path)?;
         if post_path.exists() {
             return Err(Error::PostPathExisted(path.into()));
         }
-        let now = OffsetDateTime::now_local()?;
+        let now = self.now()?;
         let content = format!(
             "created: {}\n\
              tags: [{}]\n\
@@ -390,6 +1294,28 @@ impl Mdblog {
         Ok(())
     }
 
+    /// add blog build directory into `.gitignore` file if missing.
+    pub fn export_gitignore(&self) -> Result<()> {
+        let build_dir = self.settings.build_dir.trim_end_matches('/');
+        let entry = format!("{}/", build_dir);
+        let path = self.root.join(".gitignore");
+        if !path.exists() {
+            write_file(&path, format!("{}\n", entry).as_bytes())?;
+            return Ok(());
+        }
+        let content = std::fs::read_to_string(&path).map_err(|e| Error::FileRead(path.clone(), e))?;
+        if content.lines().map(str::trim).any(|line| line == entry || line == build_dir) {
+            return Ok(());
+        }
+        let write_error = |e| Error::FileWrite(path.clone(), e);
+        let mut file = std::fs::OpenOptions::new().append(true).open(&path).map_err(write_error)?;
+        if !content.is_empty() && !content.ends_with('\n') {
+            file.write_all(b"\n").map_err(write_error)?;
+        }
+        file.write_all(format!("{}\n", entry).as_bytes()).map_err(write_error)?;
+        Ok(())
+    }
+
     fn media_dest<P: AsRef<Path>>(&self, media: P) -> Result<PathBuf> {
         let build_dir = self.build_root_dir()?;
         let rel_path = media.as_ref().strip_prefix(&self.media_root_dir()?)?.to_owned();
@@ -407,30 +1333,113 @@ impl Mdblog {
         for entry in walker.filter_entry(|e| !is_hidden(e)) {
             let entry = entry.expect("get walker entry error");
             let src_path = entry.path();
+            let dest = self.media_dest(src_path)?;
             if src_path.is_dir() {
-                std::fs::create_dir_all(self.media_dest(src_path)?)?;
+                std::fs::create_dir_all(&dest).map_err(|e| Error::DirCreate(dest, e))?;
                 continue;
             }
-            std::fs::copy(src_path, self.media_dest(src_path)?)?;
+            std::fs::copy(src_path, &dest).map_err(|e| Error::FileCopy(src_path.into(), dest, e))?;
         }
         Ok(())
     }
 
     /// export blog static files.
+    ///
+    /// skipped if `theme_static` setting is false,
+    /// the files identical to the ones of other themes in multiple themes build are hard linked.
     pub fn export_static(&self) -> Result<()> {
+        if !self.settings.theme_static {
+            debug!("skip exporting theme({}) static", self.theme.name);
+            return Ok(());
+        }
         let build_dir = self.build_root_dir()?;
-        self.theme.export_static(&build_dir)?;
+        let mut shared = self.shared_files.borrow_mut();
+        self.theme.export_static_files(build_dir, &self.settings.cache_bust, &self.settings.static_dir, &mut shared)?;
         Ok(())
     }
 
     /// export blog posts.
+    ///
+    /// the posts are rendered by `Settings::jobs` threads in parallel, link posts are redirect pages,
+    /// only the posts created on or after the `since` date are rendered if it is set.
     pub fn export_posts(&self) -> Result<()> {
         let build_dir = self.build_root_dir()?;
-        for post in &self.posts {
+        let jobs = self.build_jobs();
+        let posts: Vec<&Rc<Post>> = match self.since {
+            Some(since) => self.posts.iter().filter(|p| p.headers.created.date() >= since).collect(),
+            None => self.posts.iter().collect(),
+        };
+        // link posts are redirected to their `redirect_to` url
+        let (links, posts): (Vec<&Rc<Post>>, Vec<&Rc<Post>>) =
+            posts.into_iter().partition(|p| !p.headers.redirect_to.is_empty());
+        for post in links {
             let dest = build_dir.join(post.dest());
-            let html = self.render_post(post)?;
+            debug!(path:% = dest.display(); "rendering link post({}) ...", dest.display());
+            let html = redirect_html(&post.title, &post.headers.redirect_to, self.settings.noindex);
             write_file(&dest, html.as_bytes())?;
         }
+        if jobs <= 1 || posts.len() <= 1 {
+            for post in posts {
+                let dest = build_dir.join(post.dest());
+                let html = self.render_post(post)?;
+                write_file(&dest, html.as_bytes())?;
+            }
+            return Ok(());
+        }
+
+        let base = self.get_base_context()?;
+        let pages: Vec<(PathBuf, Context, String)> = posts
+            .into_iter()
+            .map(|post| {
+                let context = self.post_context(base.clone(), post);
+                (build_dir.join(post.dest()), context, self.post_debug_comment(post))
+            })
+            .collect();
+        let renderer = &self.theme.renderer;
+        let chunk_size = (pages.len() + jobs - 1) / jobs;
+        thread::scope(|scope| {
+            let handles: Vec<_> = pages
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    scope.spawn(move || -> Result<()> {
+                        for (dest, context, comment) in chunk {
+                            debug!(path:% = dest.display(); "rendering post({}) ...", dest.display());
+                            let html = comment.to_owned() + &renderer.render("post.tpl", context)?;
+                            write_file(dest, html.as_bytes())?;
+                        }
+                        Ok(())
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .try_for_each(|handle| handle.join().expect("post render thread panicked"))
+        })
+    }
+
+    /// export blog static pages.
+    pub fn export_pages(&self) -> Result<()> {
+        let build_dir = self.build_root_dir()?;
+        for page in &self.pages {
+            let dest = build_dir.join(page.dest());
+            let html = self.render_post(page)?;
+            write_file(&dest, html.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// copy the hand-written `.html` files without front matter into the build directory verbatim.
+    pub fn export_passthrough(&self) -> Result<()> {
+        let build_dir = self.build_root_dir()?;
+        for path in self.passthrough_paths()? {
+            let src = self.root.join(&path);
+            let dest = build_dir.join(&path);
+            debug!(path:% = path.display(); "copying html file({}) ...", path.display());
+            if let Some(parent) = dest.parent() {
+                std::fs::create_dir_all(parent).map_err(|e| Error::DirCreate(parent.into(), e))?;
+            }
+            std::fs::copy(&src, &dest).map_err(|e| Error::FileCopy(src, dest, e))?;
+        }
         Ok(())
     }
 
@@ -438,7 +1447,7 @@ impl Mdblog {
     pub fn export_index(&self) -> Result<()> {
         let build_dir = self.build_root_dir()?;
         for (i, page) in self.index_pages.iter().enumerate() {
-            let dest = build_dir.join(&page.name);
+            let dest = build_dir.join(url_dest(&page.url));
             let html = self.render_index(i)?;
             write_file(&dest, html.as_bytes())?;
         }
@@ -447,8 +1456,12 @@ impl Mdblog {
 
     /// export blog tags page.
     pub fn export_tags(&self) -> Result<()> {
+        if !self.theme.has_template("tags.tpl") {
+            warn!("theme({}) has no tags.tpl, skip exporting tags page", self.theme.name);
+            return Ok(());
+        }
         let build_dir = self.build_root_dir()?;
-        let dest = build_dir.join("tags.html");
+        let dest = build_dir.join(url_dest(&self.page_url("/tags.html")));
         let html = self.render_tags()?;
         write_file(&dest, html.as_bytes())?;
         Ok(())
@@ -459,8 +1472,8 @@ impl Mdblog {
         let build_dir = self.build_root_dir()?;
         if let Some(pages) = self.tag_pages.get(&tag.name) {
             for (i, page) in pages.iter().enumerate() {
-                let dest = build_dir.join("tags").join(&page.name);
-                debug!("rendering tag: {} ...", dest.display());
+                let dest = build_dir.join(url_dest(&page.url));
+                debug!(path:% = dest.display(); "rendering tag: {} ...", dest.display());
                 let html = self.render_tag(tag, i)?;
                 write_file(&dest, html.as_bytes())?;
             }
@@ -470,10 +1483,14 @@ impl Mdblog {
 
     /// export blog atom.xml
     pub fn export_atom(&self) -> Result<()> {
+        if !self.theme.has_template("atom.tpl") {
+            warn!("theme({}) has no atom.tpl, skip exporting atom.xml", self.theme.name);
+            return Ok(());
+        }
         debug!("rendering atom ...");
         let build_dir: PathBuf = self.build_root_dir()?;
         let dest: PathBuf = build_dir.join("atom.xml");
-        let now: OffsetDateTime = OffsetDateTime::now_local()?;
+        let now: OffsetDateTime = self.now()?;
         let mut context: Context = self.get_base_context()?;
         context.insert("now", &now.format(&Rfc3339)?);
         context.insert("posts", &self.posts[..10.min(self.posts.len())]);
@@ -482,51 +1499,377 @@ impl Mdblog {
         Ok(())
     }
 
+    /// export redirect pages of blog post aliases.
+    pub fn export_aliases(&self) -> Result<()> {
+        let build_dir = self.build_root_dir()?;
+        let mut dests: BTreeMap<PathBuf, &Post> = self.posts.iter().map(|p| (p.dest(), &**p)).collect();
+        let generated = self.generated_dests();
+        for post in &self.posts {
+            for alias in &post.headers.aliases {
+                let mut dest = PathBuf::from(alias.trim_start_matches('/'));
+                if !dest.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir)) {
+                    return Err(Error::PostAliasInvalid(alias.clone(), post.path.clone()));
+                }
+                if alias.ends_with('/') || dest.as_os_str().is_empty() {
+                    dest.push("index.html");
+                }
+                if let Some(other) = dests.insert(dest.clone(), &**post) {
+                    return Err(Error::PostAliasCollision(alias.clone(), post.path.clone(), other.path.clone()));
+                }
+                if generated.contains(&dest) {
+                    return Err(Error::PostAliasOverwrite(alias.clone(), post.path.clone(), dest));
+                }
+                debug!(path:% = dest.display(); "rendering alias: {} ...", dest.display());
+                let url = format!("{}{}", self.settings.site_url, percent_encode_path(&post.formatted_path));
+                let html = redirect_html(&post.title, &url, self.settings.noindex);
+                write_file(&build_dir.join(&dest), html.as_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// the build directory paths of the generated pages and files other than posts, e.g. for alias collisions.
+    fn generated_dests(&self) -> BTreeSet<PathBuf> {
+        let mut dests: BTreeSet<PathBuf> = self.pages.iter().map(|p| p.dest()).collect();
+        dests.insert(PathBuf::from("index.html"));
+        for page in self.index_pages.iter().chain(self.tag_pages.values().flatten()) {
+            dests.insert(url_dest(&page.url));
+        }
+        dests.insert(url_dest(&self.page_url("/tags.html")));
+        dests.insert(PathBuf::from("atom.xml"));
+        if self.settings.latest_page {
+            dests.insert(PathBuf::from("latest/index.html"));
+        }
+        for dest in self.theme.static_manifest(&self.settings.cache_bust, &self.settings.static_dir).values() {
+            dests.insert(PathBuf::from(dest.split('?').next().unwrap_or(dest)));
+        }
+        for file in &self.settings.extra_files {
+            dests.insert(PathBuf::from(file.dest.trim_start_matches('/')));
+        }
+        dests
+    }
+
+    /// export `latest/index.html` redirecting to the newest post if `latest_page` is enabled.
+    pub fn export_latest(&self) -> Result<()> {
+        if !self.settings.latest_page {
+            return Ok(());
+        }
+        let post = match self.posts.iter().filter(|p| !p.headers.hidden).max_by_key(|p| p.headers.created) {
+            Some(post) => post,
+            None => {
+                debug!("no post, skip exporting latest page");
+                return Ok(());
+            }
+        };
+        debug!("rendering latest page ...");
+        let dest = self.build_root_dir()?.join("latest/index.html");
+        let url = format!("{}{}", self.settings.site_url, percent_encode_path(&post.formatted_path));
+        write_file(&dest, redirect_html(&post.title, &url, self.settings.noindex).as_bytes())?;
+        Ok(())
+    }
+
+    /// export blog extra files.
+    pub fn export_extra_files(&self) -> Result<()> {
+        let build_dir = self.build_root_dir()?;
+        for file in &self.settings.extra_files {
+            let src = get_dir(&self.root, &file.src)?;
+            if !src.is_file() {
+                return Err(Error::ExtraFileNotFound(src));
+            }
+            let dest = build_dir.join(file.dest.trim_start_matches('/'));
+            debug!(path:% = dest.display(); "exporting extra file: {} ...", dest.display());
+            let mut buf = Vec::new();
+            read_file(&src, &mut buf)?;
+            write_file(&dest, &buf)?;
+        }
+        Ok(())
+    }
+
+    /// export blog build manifest `manifest.json`, see `Manifest`.
+    ///
+    /// skipped if `build_manifest` setting is false.
+    pub fn export_manifest(&self) -> Result<()> {
+        if !self.settings.build_manifest {
+            return Ok(());
+        }
+        debug!("exporting manifest ...");
+        let build_dir = self.build_root_dir()?;
+        let media_root_dir = self.media_root_dir()?;
+        let sources: BTreeMap<PathBuf, PathBuf> =
+            self.posts.iter().chain(&self.pages).map(|p| (build_dir.join(p.dest()), p.src())).collect();
+        let pruned = self.pruned_orphans()?;
+        let mut files = BTreeMap::new();
+        for entry in WalkDir::new(&build_dir) {
+            let entry = entry.expect("get walker entry error");
+            let path = entry.path();
+            if !path.is_file() || pruned.contains(path) {
+                continue;
+            }
+            let rel_path = path.strip_prefix(&build_dir)?;
+            let name = rel_path.to_string_lossy().replace('\\', "/");
+            if name == "manifest.json" {
+                continue;
+            }
+            let source = match rel_path.strip_prefix("media") {
+                Ok(media) => Some(media_root_dir.join(media)),
+                Err(_) => sources.get(path).cloned(),
+            };
+            let source_mtime = source
+                .and_then(|src| std::fs::metadata(src).ok())
+                .and_then(|meta| meta.modified().ok())
+                .and_then(|mtime| mtime.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_secs());
+            let mut buf = Vec::new();
+            read_file(path, &mut buf)?;
+            let hash = format!("{:08x}", content_hash(&buf));
+            files.insert(name, ManifestEntry { hash, source_mtime });
+        }
+        let manifest = Manifest {
+            version: MANIFEST_VERSION,
+            files,
+        };
+        let content = serde_json::to_string_pretty(&manifest)?;
+        write_file(&build_dir.join("manifest.json"), content.as_bytes())?;
+        Ok(())
+    }
+
+    /// export the `_headers` file of `Cache-Control` hints, see `Settings::headers_file`.
+    ///
+    /// the fingerprinted static files are cached for a year, the html pages are revalidated on every request,
+    /// only the files produced by the build are listed.
+    pub fn export_headers_file(&self) -> Result<()> {
+        if !self.settings.headers_file {
+            return Ok(());
+        }
+        debug!("exporting _headers ...");
+        let build_dir = self.build_root_dir()?;
+        let mut content = String::new();
+        if let "filename" | "true" = self.settings.cache_bust.as_str() {
+            let manifest = self.theme.static_manifest(&self.settings.cache_bust, &self.settings.static_dir);
+            // the bundled files share the path of their bundle
+            for dest in manifest.values().collect::<BTreeSet<_>>() {
+                if build_dir.join(dest).is_file() {
+                    content.push_str(&format!("/{}\n  Cache-Control: public, max-age=31536000, immutable\n\n", dest));
+                }
+            }
+        }
+        let pruned = self.pruned_orphans()?;
+        for entry in WalkDir::new(&build_dir).sort_by_file_name() {
+            let entry = entry.expect("get walker entry error");
+            let path = entry.path();
+            if !path.is_file() || pruned.contains(path) || path.extension().map_or(true, |ext| ext != "html") {
+                continue;
+            }
+            let name = path.strip_prefix(&build_dir)?.to_string_lossy().replace('\\', "/");
+            let url = percent_encode_path(&format!("/{}", name));
+            content.push_str(&format!("{}\n  Cache-Control: public, max-age=0, must-revalidate\n\n", url));
+        }
+        write_file(&build_dir.join("_headers"), content.as_bytes())?;
+        Ok(())
+    }
+
     /// get base context of `theme.renderer` templates
     fn get_base_context(&self) -> Result<Context> {
         let mut context = Context::new();
         context.insert("config", &self.settings);
+        // a separate object never collides with the other context names like `post`
+        context.insert("globals", &self.settings.globals);
+        context.insert("assets", &self.theme.static_manifest(&self.settings.cache_bust, &self.settings.static_dir));
+        if self.settings.inline_css {
+            context.insert("main_css", &String::from_utf8_lossy(self.theme.main_css()));
+        }
         let mut tags = self.tags_map.values().collect::<Vec<_>>();
         tags.sort_by_key(|x| x.name.to_lowercase());
         context.insert("tags", &tags);
-        context.insert("tag_map", &self.tags_map);
+        // `tag_map` is keyed by the tag names of posts, the merged tag names share one tag
+        let mut tag_map: BTreeMap<&str, &Tag> = BTreeMap::new();
+        for (slug, tag) in &self.tags_map {
+            for name in tag.posts.iter().flat_map(|p| &p.headers.tags) {
+                if slugify(name) == *slug {
+                    tag_map.insert(name, tag);
+                }
+            }
+        }
+        context.insert("tag_map", &tag_map);
         context.insert("index_pages", &self.index_pages);
         context.insert("tag_pages", &self.tag_pages);
+        context.insert("pages", &self.pages);
+        context.insert("feed_url", &self.feed_url());
+        context.insert("sitemap_url", &self.sitemap_url());
+        context.insert("home_url", &self.page_url("/index.html"));
+        context.insert("index_url", &self.index_page_url("index.html"));
+        context.insert("tags_url", &self.page_url("/tags.html"));
         Ok(context)
     }
 
-    /// render post.html.
-    pub fn render_post(&self, post: &Post) -> Result<String> {
-        debug!("rendering post({}) ...", post.path.display());
-        let mut context = self.get_base_context()?;
+    /// the atom feed url, `None` if the theme has no `atom.tpl`.
+    fn feed_url(&self) -> Option<String> {
+        if !self.theme.has_template("atom.tpl") {
+            return None;
+        }
+        Some(format!("{}/atom.xml", self.settings.site_url))
+    }
+
+    /// the sitemap url, `None` as no sitemap is exported yet, it must stay `None` with the `noindex` setting.
+    fn sitemap_url(&self) -> Option<String> {
+        None
+    }
+
+    /// the full title of the page titled `page`, see `Settings::title_format`.
+    fn page_title(&self, page: &str) -> String {
+        self.settings
+            .title_format
+            .replace("{page}", page)
+            .replace("{site}", &self.settings.site_name)
+    }
+
+    /// post.html context from the `base` context.
+    ///
+    /// it adds the `post`, its `page_title`, the `post_footer_html`
+    /// and a `page_type` of `page` for static pages or `post` for the others.
+    fn post_context(&self, base: Context, post: &Post) -> Context {
+        let mut context = base;
+        context.insert("page_type", if post.is_page() { "page" } else { "post" });
+        context.insert("page_title", &self.page_title(&post.title));
         context.insert("post", &post);
-        Ok(self.theme.renderer.render("post.tpl", &context)?)
+        context.insert("post_footer_html", &self.post_footer_html);
+        context
+    }
+
+    /// the html comment of post source path prepended to post.html, see `Settings::debug_comments`.
+    fn post_debug_comment(&self, post: &Post) -> String {
+        if !self.settings.debug_comments {
+            return String::new();
+        }
+        format!("<!-- source: {} -->\n", post.path.to_string_lossy().replace('\\', "/"))
+    }
+
+    /// render post.html, static pages are rendered by `page.tpl` if the theme has it.
+    pub fn render_post(&self, post: &Post) -> Result<String> {
+        debug!(path:% = post.path.display(); "rendering post({}) ...", post.path.display());
+        let context = self.post_context(self.get_base_context()?, post);
+        let template = if post.is_page() && self.theme.has_template("page.tpl") {
+            "page.tpl"
+        } else {
+            "post.tpl"
+        };
+        let html = self.theme.renderer.render(template, &context)?;
+        Ok(self.post_debug_comment(post) + &html)
+    }
+
+    /// render the post of `post_path` into a self-contained html file, returns the file path.
+    ///
+    /// the theme css and the local images of the post are inlined, hidden posts can also be rendered.
+    pub fn build_single_file(&mut self, post_path: &Path) -> Result<PathBuf> {
+        // the single file mode is restored afterwards, also on errors
+        let drafts = std::mem::replace(&mut self.drafts, true);
+        let inline_css = std::mem::replace(&mut self.settings.inline_css, true);
+        let result = self.export_single_file(post_path);
+        self.drafts = drafts;
+        self.settings.inline_css = inline_css;
+        result
+    }
+
+    /// render the post of `post_path` into a self-contained html file in single file mode, see `build_single_file`.
+    fn export_single_file(&mut self, post_path: &Path) -> Result<PathBuf> {
+        let post_path = if post_path.is_absolute() {
+            post_path.strip_prefix(&self.root)?
+        } else {
+            post_path
+        };
+        self.load_posts()?;
+        self.load_post_footer();
+        self.build_index_pages()?;
+        self.build_tag_pages()?;
+        let post = match self.posts.iter().chain(&self.pages).find(|p| p.path == post_path) {
+            Some(post) => Rc::clone(post),
+            None => return Err(Error::PostNotFound(post_path.into())),
+        };
+
+        let media_root_dir = self.media_root_dir()?;
+        let html = self.render_post(&post)?;
+        let html = replace_attr_values(&html, "src", |link| {
+            let url = resolve_link(&post.formatted_path, link, &self.settings.site_url)?;
+            let path = match url.strip_prefix("/media/") {
+                Some(rel_path) => media_root_dir.join(rel_path),
+                None => self.root.join(url.trim_start_matches('/')),
+            };
+            let mut buf = Vec::new();
+            read_file(&path, &mut buf).ok()?;
+            Some(format!("data:{};base64,{}", mime_type(&path), base64_encode(&buf)))
+        });
+
+        let dest = self.build_root_dir()?.join(post.dest().with_extension("single.html"));
+        write_file(&dest, html.as_bytes())?;
+        info!(path:% = dest.display(); "single file: {}", dest.display());
+        Ok(dest)
+    }
+
+    /// render the post file `path` through the theme into html without writing any file, e.g. for previews.
+    ///
+    /// the file needs not be under the blog root, the other blog posts are not loaded,
+    /// so only the tags of the post are listed and `post:<slug>` links are left alone.
+    pub fn render_file(&mut self, path: &Path) -> Result<String> {
+        let (root, rel_path) = match path.strip_prefix(&self.root) {
+            Ok(rel_path) => (self.root.clone(), rel_path.to_owned()),
+            Err(_) if path.is_relative() => (self.root.clone(), path.to_owned()),
+            Err(_) => {
+                let root = path.parent().unwrap_or_else(|| Path::new("/")).to_owned();
+                (root, PathBuf::from(path.file_name().unwrap_or_default()))
+            }
+        };
+        let mut post = Post::with_settings(root.as_path(), rel_path.as_path(), &self.settings, self.renderer.as_ref())?;
+        post.normalize_url(&self.settings.trailing_slash);
+        let post = Rc::new(post);
+        self.tags_map.clear();
+        add_post_tags(&mut self.tags_map, &post);
+        self.build_tag_pages()?;
+        self.load_post_footer();
+        self.render_post(&post)
     }
 
     /// render index*.html.
+    ///
+    /// the context has a `page_type` of `index`, the `page` and its `posts`, the `full_content` flag,
+    /// the first page also has the `index_content`.
     pub fn render_index(&self, i: usize) -> Result<String> {
         debug!("rendering index ...");
         let mut context = self.get_base_context()?;
+        context.insert("page_type", "index");
+        context.insert("page_title", &self.page_title(&self.settings.site_name));
         context.insert("page", &self.index_pages[i]);
         context.insert("posts", &self.index_pages[i].posts);
+        context.insert("full_content", &self.settings.index_full_content);
+        if i == 0 && !self.index_content.is_empty() {
+            context.insert("index_content", &self.index_content);
+        }
         Ok(self.theme.renderer.render("index.tpl", &context)?)
     }
 
-    /// render tags.html.
+    /// render tags.html, the context has a `page_type` of `tags`.
     pub fn render_tags(&self) -> Result<String> {
         debug!("rendering tags ...");
-        let context = self.get_base_context()?;
+        let mut context = self.get_base_context()?;
+        context.insert("page_type", "tags");
+        context.insert("page_title", &self.page_title("Tags"));
         Ok(self.theme.renderer.render("tags.tpl", &context)?)
     }
 
     /// render tag.html.
+    ///
+    /// the context has a `page_type` of `tag`, the `tag` and its `current_tag` name,
+    /// the `page` and its `posts` and the `full_content` flag.
     pub fn render_tag(&self, tag: &Tag, i: usize) -> Result<String> {
         debug!("rendering tag ...");
         let mut context = self.get_base_context()?;
         let page = self.tag_pages.get(&tag.name).unwrap().get(i).unwrap();
+        context.insert("page_type", "tag");
+        context.insert("page_title", &self.page_title(&tag.name));
+        context.insert("current_tag", &tag.name);
         context.insert("tag", &tag);
         context.insert("page", &page);
         context.insert("posts", &page.posts);
+        context.insert("full_content", &self.settings.index_full_content);
         Ok(self.theme.renderer.render("tag.tpl", &context)?)
     }
 
@@ -558,6 +1901,47 @@ impl Mdblog {
         Ok(())
     }
 
+    /// install a blog theme from a `.tar.gz` archive, returns the installed theme name.
+    ///
+    /// the theme files are at the archive top level, named after the archive file,
+    /// or in its only top level directory, named after the directory.
+    pub fn install_blog_theme(&self, archive: &Path) -> Result<String> {
+        let theme_root = self.theme_root_dir()?;
+        std::fs::create_dir_all(&theme_root).map_err(|e| Error::DirCreate(theme_root.clone(), e))?;
+        let unpack_dir = TempBuilder::new().prefix(".install.").tempdir_in(&theme_root)?;
+        let file = File::open(archive).map_err(|e| Error::FileRead(archive.into(), e))?;
+        tar::Archive::new(GzDecoder::new(file))
+            .unpack(unpack_dir.path())
+            .map_err(|e| Error::FileRead(archive.into(), e))?;
+
+        let mut entries = Vec::new();
+        for entry in std::fs::read_dir(unpack_dir.path())? {
+            entries.push(entry?.path());
+        }
+        let (src_dir, name) = match entries.as_slice() {
+            // a lone wrapper directory of the theme, not a flat archive of only `templates/`
+            [dir] if dir.join("templates").is_dir() => {
+                (dir.clone(), dir.file_name().and_then(|s| s.to_str()).map(String::from))
+            }
+            _ => {
+                let file_name = archive.file_name().and_then(|s| s.to_str()).unwrap_or("");
+                let name = file_name.trim_end_matches(".tar.gz").trim_end_matches(".tgz");
+                (unpack_dir.path().to_owned(), Some(name.to_string()).filter(|s| !s.is_empty()))
+            }
+        };
+        let name = name.ok_or_else(|| Error::ThemeArchiveInvalid(archive.into(), "theme name".into()))?;
+        // the unpacked theme must load like an installed one
+        Theme::from_dir(&theme_root, &name, &src_dir)?;
+
+        let dest_dir = theme_root.join(&name);
+        if dest_dir.exists() {
+            return Err(Error::ThemeExisted(name));
+        }
+        std::fs::rename(&src_dir, &dest_dir)?;
+        info!("installed theme({})", name);
+        Ok(name)
+    }
+
     /// delete a blog theme.
     pub fn delete_blog_theme(&self, name: &str) -> Result<()> {
         if self.settings.theme == name {
@@ -588,8 +1972,8 @@ fn is_hidden(entry: &DirEntry) -> bool {
     entry.file_name().to_str().map(|s| s.starts_with('.')).unwrap_or(false)
 }
 
-/// check directory entry is an markdown file.
-fn is_markdown_file(entry: &DirEntry) -> bool {
+/// check directory entry is an markdown file with one of the `extensions`.
+fn is_markdown_file(entry: &DirEntry, extensions: &[String]) -> bool {
     if !entry.path().is_file() {
         return false;
     }
@@ -602,9 +1986,51 @@ fn is_markdown_file(entry: &DirEntry) -> bool {
             if s.starts_with(|c| (c == '.') | (c == '~')) {
                 return false;
             }
-            return s.ends_with(".md");
+            return extensions.iter().any(|ext| s.ends_with(&format!(".{}", ext)));
+        }
+    }
+}
+
+/// check directory entry is an `.html` file.
+fn is_html_file(entry: &DirEntry) -> bool {
+    entry.path().is_file() && entry.path().extension().map_or(false, |ext| ext == "html")
+}
+
+/// check the file of `path` starts with `---` fenced front matter.
+fn has_front_matter(path: &Path) -> bool {
+    let mut buf = Vec::new();
+    if read_file(path, &mut buf).is_err() {
+        return false;
+    }
+    let content = String::from_utf8_lossy(&buf);
+    split_fenced_headers(trim_leading_blank_lines(&content)).is_some()
+}
+
+/// sort `posts` in `order`: `date_asc`, `title`, `weight` or `date_desc` by default.
+///
+/// `weight` sorts by ascending weight then title, the posts without weight are put after the weighted ones.
+fn sort_posts(posts: &mut [&Rc<Post>], order: &str) {
+    match order {
+        "date_asc" => posts.sort_by(|p1, p2| p1.headers.created.cmp(&p2.headers.created)),
+        "title" => posts.sort_by_key(|p| p.title.to_lowercase()),
+        "weight" => posts.sort_by_key(|p| (p.headers.weight.is_none(), p.headers.weight, p.title.to_lowercase())),
+        _ => posts.sort_by(|p1, p2| p2.headers.created.cmp(&p1.headers.created)),
+    }
+}
+
+/// run a git command in `dir`, returns its stdout, the failed command output is returned as error.
+fn run_git(dir: &Path, args: &[&str]) -> Result<String> {
+    let command = format!("git {}", args.join(" "));
+    debug!("running: {}", command);
+    let output = Command::new("git").arg("-C").arg(dir).args(args).output()?;
+    if !output.status.success() {
+        let mut message = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        if message.is_empty() {
+            message = String::from_utf8_lossy(&output.stdout).trim().to_string();
         }
+        return Err(Error::GitCommand(command, message));
     }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
 }
 
 /// create a directory pathbuf from setting config.
@@ -618,6 +2044,116 @@ fn get_dir<P: AsRef<Path>>(root: P, value: &str) -> Result<PathBuf> {
     }
 }
 
+/// resolve an internal `link` of the post at `post_url` to an url relative to the site root.
+///
+/// external links and pure fragment links are ignored.
+fn resolve_link(post_url: &str, link: &str, site_url: &str) -> Option<String> {
+    let mut link = link;
+    if !site_url.is_empty() && link.starts_with(site_url) {
+        link = &link[site_url.len()..];
+        if link.is_empty() {
+            link = "/";
+        }
+    }
+    let scheme_end = link.find(':').unwrap_or(link.len());
+    if link.starts_with("//") || (scheme_end < link.len() && !link[..scheme_end].contains('/')) {
+        return None;
+    }
+    let link = link.split(|c| c == '#' || c == '?').next().unwrap_or("");
+    if link.is_empty() {
+        return None;
+    }
+    let link = percent_decode(link);
+    let joined = if link.starts_with('/') {
+        link
+    } else {
+        let dir = &post_url[..post_url.rfind('/').map(|i| i + 1).unwrap_or(0)];
+        format!("{}{}", dir, link)
+    };
+    let mut parts: Vec<&str> = Vec::new();
+    for part in joined.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            _ => parts.push(part),
+        }
+    }
+    let mut url = format!("/{}", parts.join("/"));
+    if joined.ends_with('/') && !parts.is_empty() {
+        url.push('/');
+    }
+    Some(url)
+}
+
+/// the html page redirecting to `url`, asking search engines not to index it if `noindex`.
+fn redirect_html(title: &str, url: &str, noindex: bool) -> String {
+    format!(
+        "<!doctype html>\n\
+         <html>\n\
+         <head>\n\
+         <meta charset=\"utf-8\">\n\
+         {robots}\
+         <title>{title}</title>\n\
+         <link rel=\"canonical\" href=\"{url}\">\n\
+         <meta http-equiv=\"refresh\" content=\"0; url={url}\">\n\
+         </head>\n\
+         <body><a href=\"{url}\">{title}</a></body>\n\
+         </html>\n",
+        robots = if noindex { "<meta name=\"robots\" content=\"noindex, nofollow\">\n" } else { "" },
+        title = escape_html(title),
+        url = escape_html(url)
+    )
+}
+
+/// the `(start, end)` post ranges of `total` posts paginated by `per_page`,
+/// a `per_page` of `0` or not less than `total` puts all posts on one page,
+/// there is always a page, empty if there is no post.
+fn page_ranges(total: usize, per_page: usize) -> Vec<(usize, usize)> {
+    if total == 0 {
+        return vec![(0, 0)];
+    }
+    let per_page = if per_page == 0 || per_page > total {
+        total.max(1)
+    } else {
+        per_page
+    };
+    (0..total).step_by(per_page).map(|start| (start, total.min(start + per_page))).collect()
+}
+
+/// add the `post` to its tags of `tags_map`, keyed by tag slug.
+///
+/// tags of the same slug like `Rust` and `rust` share one tag page, named after the first added one.
+fn add_post_tags(tags_map: &mut BTreeMap<String, Tag>, post: &Rc<Post>) {
+    for name in &post.headers.tags {
+        let tag = tags_map.entry(slugify(name)).or_insert_with(|| Tag::new(name));
+        if tag.name != *name {
+            warn!(
+                path:% = post.path.display();
+                "tag {} of post {} has the same url as tag {}, they are merged into tag {}",
+                name,
+                post.path.display(),
+                tag.name,
+                tag.name
+            );
+        }
+        // a post tagged with both `Rust` and `rust` is added once
+        if tag.posts.last().map_or(false, |p| Rc::ptr_eq(p, post)) {
+            continue;
+        }
+        tag.add(post.clone());
+    }
+}
+
+fn format_tag_page_name(slug: &str, page: usize) -> String {
+    if page <= 1 {
+        format!("{}.html", slug)
+    } else {
+        format!("{}/page/{}/index.html", slug, page)
+    }
+}
+
 fn format_page_name(prefix: &str, page: usize, total: usize) -> String {
     if page == 0 || page > total {
         return String::default();
@@ -630,3 +2166,168 @@ fn format_page_name(prefix: &str, page: usize, total: usize) -> String {
     s.push_str(".html");
     s
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_post(root: &Path, rel_path: &str, content: &str) {
+        let path = root.join(rel_path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    fn test_post(root: &Path, rel_path: &str, headers: &str) -> Rc<Post> {
+        write_post(root, rel_path, &format!("---\n{}\n---\nbody\n", headers));
+        let post = Post::new(root, Path::new(rel_path)).unwrap();
+        Rc::new(post)
+    }
+
+    fn sorted_titles(posts: &[Rc<Post>], order: &str) -> Vec<String> {
+        let mut posts: Vec<_> = posts.iter().collect();
+        sort_posts(&mut posts, order);
+        posts.iter().map(|p| p.title.clone()).collect()
+    }
+
+    fn sort_test_posts(root: &Path) -> Vec<Rc<Post>> {
+        vec![
+            test_post(root, "posts/b.md", "title: banana\ncreated: 2021-01-02T00:00:00Z\nweight: 2"),
+            test_post(root, "posts/a.md", "title: Apple\ncreated: 2021-01-03T00:00:00Z"),
+            test_post(root, "posts/c.md", "title: cherry\ncreated: 2021-01-01T00:00:00Z\nweight: 1"),
+        ]
+    }
+
+    #[test]
+    fn sort_posts_by_date_desc() {
+        let root = tempfile::tempdir().unwrap();
+        let posts = sort_test_posts(root.path());
+        assert_eq!(sorted_titles(&posts, "date_desc"), ["Apple", "Banana", "Cherry"]);
+    }
+
+    #[test]
+    fn sort_posts_by_date_asc() {
+        let root = tempfile::tempdir().unwrap();
+        let posts = sort_test_posts(root.path());
+        assert_eq!(sorted_titles(&posts, "date_asc"), ["Cherry", "Banana", "Apple"]);
+    }
+
+    #[test]
+    fn sort_posts_by_title() {
+        let root = tempfile::tempdir().unwrap();
+        let posts = sort_test_posts(root.path());
+        assert_eq!(sorted_titles(&posts, "title"), ["Apple", "Banana", "Cherry"]);
+    }
+
+    #[test]
+    fn sort_posts_by_weight() {
+        let root = tempfile::tempdir().unwrap();
+        let posts = sort_test_posts(root.path());
+        assert_eq!(sorted_titles(&posts, "weight"), ["Cherry", "Banana", "Apple"]);
+    }
+
+    #[test]
+    fn validate_finds_posts_of_the_same_url() {
+        let root = tempfile::tempdir().unwrap();
+        write_post(root.path(), "posts/2021-01-01-hello.md", "---\ntags: [hello]\n---\nhello\n");
+        let created = "created: 2021-01-02T00:00:00Z";
+        write_post(root.path(), "posts/hello.md", &format!("---\n{}\ntags: [hello]\n---\nhello again\n", created));
+        write_post(root.path(), "posts/world.md", &format!("---\n{}\ntags: [world]\n---\nworld\n", created));
+        let mdblog = Mdblog::new(root.path()).unwrap();
+        let duplicated: Vec<_> = mdblog
+            .validate()
+            .unwrap()
+            .into_iter()
+            .filter(|e| matches!(e, Error::PostSlugDuplicated(_, _)))
+            .collect();
+        assert_eq!(duplicated.len(), 1);
+    }
+
+    #[test]
+    fn tags_of_the_same_slug_are_merged() {
+        let root = tempfile::tempdir().unwrap();
+        write_post(root.path(), "posts/a.md", "---\ncreated: 2021-01-02T00:00:00Z\ntags: [rust]\n---\na\n");
+        write_post(root.path(), "posts/b.md", "---\ncreated: 2021-01-01T00:00:00Z\ntags: [Rust]\n---\nb\n");
+        let mut mdblog = Mdblog::new(root.path()).unwrap();
+        mdblog.load_posts().unwrap();
+        assert_eq!(mdblog.tags_map.len(), 1);
+        let tag = &mdblog.tags_map["rust"];
+        assert_eq!(tag.name, "Rust");
+        assert_eq!(tag.num, 2);
+    }
+
+    #[test]
+    fn install_flat_theme_archive() {
+        let root = tempfile::tempdir().unwrap();
+        let path = root.path().join("flat.tar.gz");
+        let mut archive = tar::Builder::new(flate2::write::GzEncoder::new(
+            File::create(&path).unwrap(),
+            flate2::Compression::default(),
+        ));
+        archive.append_dir_all("templates", "src/themes/simple/templates").unwrap();
+        archive.into_inner().unwrap().finish().unwrap();
+        let mdblog = Mdblog::new(root.path()).unwrap();
+        assert_eq!(mdblog.install_blog_theme(&path).unwrap(), "flat");
+        assert!(root.path().join("_themes/flat/templates/base.tpl").is_file());
+    }
+
+    #[test]
+    fn export_aliases_rejects_unsafe_aliases() {
+        for (alias, invalid) in [("../escape.html", true), ("/tags.html", false), ("/index.html", false)] {
+            let root = tempfile::tempdir().unwrap();
+            let headers = format!("created: 2021-01-01T00:00:00Z\naliases: [{:?}]", alias);
+            write_post(root.path(), "posts/a.md", &format!("---\n{}\n---\na\n", headers));
+            let mut mdblog = Mdblog::new(root.path()).unwrap();
+            mdblog.load_posts().unwrap();
+            mdblog.build_index_pages().unwrap();
+            mdblog.build_tag_pages().unwrap();
+            match mdblog.export_aliases() {
+                Err(Error::PostAliasInvalid(..)) => assert!(invalid, "{}", alias),
+                Err(Error::PostAliasOverwrite(..)) => assert!(!invalid, "{}", alias),
+                other => panic!("alias {} exported: {:?}", alias, other.map_err(|e| e.to_string())),
+            }
+        }
+    }
+
+    #[test]
+    fn build_single_file_restores_settings() {
+        let root = tempfile::tempdir().unwrap();
+        write_post(root.path(), "posts/a.md", "---\ncreated: 2021-01-01T00:00:00Z\n---\na\n");
+        let mut mdblog = Mdblog::new(root.path()).unwrap();
+        assert!(mdblog.build_single_file(Path::new("posts/a.md")).is_ok());
+        assert!(mdblog.build_single_file(Path::new("posts/missing.md")).is_err());
+        assert!(!mdblog.drafts);
+        assert!(!mdblog.settings.inline_css);
+    }
+
+    #[test]
+    fn redirect_html_noindex() {
+        let robots = "<meta name=\"robots\" content=\"noindex, nofollow\">";
+        assert!(redirect_html("hello", "/posts/hello.html", true).contains(robots));
+        assert!(!redirect_html("hello", "/posts/hello.html", false).contains("robots"));
+    }
+
+    #[test]
+    fn page_ranges_of_no_post() {
+        assert_eq!(page_ranges(0, 0), vec![(0, 0)]);
+        assert_eq!(page_ranges(0, 1), vec![(0, 0)]);
+        assert_eq!(page_ranges(0, 10), vec![(0, 0)]);
+    }
+
+    #[test]
+    fn page_ranges_by_posts_per_page() {
+        assert_eq!(page_ranges(3, 0), vec![(0, 3)]);
+        assert_eq!(page_ranges(3, 1), vec![(0, 1), (1, 2), (2, 3)]);
+        assert_eq!(page_ranges(3, 2), vec![(0, 2), (2, 3)]);
+        assert_eq!(page_ranges(3, 10), vec![(0, 3)]);
+    }
+
+    #[test]
+    fn index_page_without_posts() {
+        let root = tempfile::tempdir().unwrap();
+        let mut mdblog = Mdblog::new(root.path()).unwrap();
+        mdblog.build_index_pages().unwrap();
+        assert_eq!(mdblog.index_pages.len(), 1);
+        assert_eq!(mdblog.index_pages[0].name, "index.html");
+        assert!(mdblog.index_pages[0].posts.is_empty());
+    }
+}
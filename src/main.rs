@@ -1,15 +1,23 @@
 use std::env;
 use std::error::Error;
+use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::process::Command;
 
-use clap::{Parser, Subcommand};
-use log::error;
+use clap::{Args, Parser, Subcommand};
+use log::{error, info};
 use mdblog::{Mdblog, Result};
 
 /// static site generator from markdown files
 #[derive(Parser, Debug)]
 #[clap(author, version, about)]
 struct Cli {
+    #[clap(short, long, global = true)]
+    /// Print only warnings and errors, nothing on success
+    quiet: bool,
+    #[clap(long, global = true, value_name = "format", default_value = "human", value_parser = ["human", "json"])]
+    /// Log output format, `json` prints a JSON object of level, target, message and optional path per line
+    log_format: String,
     #[clap(subcommand)]
     cmd: CliCommand,
 }
@@ -28,10 +36,47 @@ enum CliCommand {
         tags: Vec<String>,
         /// Post path relative to blog `posts` directory
         path: PathBuf,
+        #[clap(long)]
+        /// Open the created post with `$VISUAL` or `$EDITOR`
+        open: bool,
     },
     /// Build the blog static files
-    Build,
+    #[clap(after_help = "`--serve` builds once into a temporary directory and serves it,\n\
+                         the build directory is left alone,\n\
+                         `--serve --watch` also rebuilds on change and keeps serving.")]
+    Build(BuildArgs),
+    /// Check the blog posts without building
+    Check,
+    /// Build the blog into a directory, e.g. a `gh-pages` worktree
+    Deploy {
+        /// the deploy directory
+        dir: PathBuf,
+        #[clap(long)]
+        /// Commit the deploy directory with git
+        commit: bool,
+    },
+    /// Diagnose common blog setup problems
+    Doctor,
+    /// Fence the blank line separated post headers with `---`
+    Migrate {
+        #[clap(long)]
+        /// Print the posts to migrate without rewriting them
+        dry_run: bool,
+    },
+    /// Render a post file through the theme and print the html, no file is written
+    Render {
+        /// the post file path
+        path: PathBuf,
+    },
+    /// Print the blog content statistics
+    Stats {
+        #[clap(long)]
+        /// Print as json
+        json: bool,
+    },
     /// Serve the blog, rebuild on change
+    #[clap(after_help = "hidden posts(`hidden: true` or `draft: true`) are served unless `--no-drafts`,\n\
+                         `build` never includes them.")]
     Serve {
         #[clap(long, default_value = "127.0.0.1")]
         /// Serve the blog at <host>
@@ -39,6 +84,15 @@ enum CliCommand {
         #[clap(short, long, default_value = "5000")]
         /// Serve the blog at <port>
         port: u16,
+        #[clap(long)]
+        /// Open the blog with browser
+        open: bool,
+        #[clap(long)]
+        /// Do not serve hidden(draft) posts
+        no_drafts: bool,
+        #[clap(long)]
+        /// Serve the posts created in the future
+        future: bool,
     },
     /// Blog theme operations
     Theme {
@@ -47,6 +101,58 @@ enum CliCommand {
     },
 }
 
+#[derive(Args, Debug)]
+struct BuildArgs {
+    #[clap(requires = "single_file")]
+    /// Post path to build, used with `--single-file`
+    post: Option<PathBuf>,
+    #[clap(long, requires = "post", conflicts_with = "serve")]
+    /// Build the post into a self-contained html file, css and images inlined
+    single_file: bool,
+    #[clap(long, conflicts_with_all = ["serve", "post"])]
+    /// Build only a maintenance page into `index.html`, posts are ignored
+    maintenance: bool,
+    #[clap(long)]
+    /// Serve the blog after building
+    serve: bool,
+    #[clap(long, requires = "serve")]
+    /// Rebuild the blog on change, requires `--serve`
+    watch: bool,
+    #[clap(long, requires = "serve")]
+    /// Open the blog with browser, requires `--serve`
+    open: bool,
+    #[clap(long, default_value = "127.0.0.1")]
+    /// Serve the blog at <host>, used with `--serve`
+    host: String,
+    #[clap(short, long, default_value = "5000")]
+    /// Serve the blog at <port>, used with `--serve`
+    port: u16,
+    #[clap(name = "theme", long = "theme")]
+    /// Build themes, every theme is built into its own subdirectory
+    themes: Vec<String>,
+    #[clap(long)]
+    /// Do not export theme static files
+    no_theme_static: bool,
+    #[clap(short, long)]
+    /// Number of parallel build jobs, `1` builds serially [default: number of CPUs]
+    jobs: Option<usize>,
+    #[clap(long)]
+    /// Build the posts created in the future
+    future: bool,
+    #[clap(long, value_name = "YYYY-MM-DD")]
+    /// Only render the posts created on or after the date
+    since: Option<String>,
+    #[clap(long, conflicts_with = "serve")]
+    /// Print the elapsed time of each build phase
+    timing: bool,
+    #[clap(long)]
+    /// Delete the output files not written by the build, they are only warned without it
+    prune: bool,
+    #[clap(long)]
+    /// Fail if no post is rendered, recommended for CI
+    fail_on_empty: bool,
+}
+
 #[derive(Parser, Debug)]
 enum ThemeCommand {
     /// list blog themes
@@ -66,19 +172,64 @@ enum ThemeCommand {
         /// theme name
         name: String,
     },
+    /// Install a theme from a `.tar.gz` archive
+    Install {
+        /// theme archive path
+        path: PathBuf,
+    },
+    /// Export the bundled `simple` theme files, e.g. to study or fork it
+    Export {
+        /// the new directory to export into
+        dir: PathBuf,
+    },
 }
 
 fn main() {
-    env_logger::Builder::from_default_env()
-        .filter(None, log::LevelFilter::Info)
-        .init();
-
     let cli = Cli::parse();
+    let level = if cli.quiet {
+        log::LevelFilter::Warn
+    } else {
+        log::LevelFilter::Info
+    };
+    let mut logger = env_logger::Builder::from_default_env();
+    logger.filter(None, level);
+    if cli.log_format == "json" {
+        logger.format(|buf, record| {
+            let mut line = serde_json::json!({
+                "level": record.level().as_str(),
+                "target": record.target(),
+                "message": record.args().to_string(),
+            });
+            // the post or file path of the record, see `path:% = ...` log key-values
+            if let Some(path) = record.key_values().get(log::kv::Key::from("path")) {
+                line["path"] = path.to_string().into();
+            }
+            writeln!(buf, "{}", line)
+        });
+    }
+    logger.init();
+
     let res = match cli.cmd {
         CliCommand::Init { ref name } => init(name),
-        CliCommand::New { ref tags, ref path } => new(path, tags),
-        CliCommand::Build => build(),
-        CliCommand::Serve { host, port } => serve(host, port),
+        CliCommand::New {
+            ref tags,
+            ref path,
+            open,
+        } => new(path, tags, open),
+        CliCommand::Build(args) => build(args),
+        CliCommand::Check => check(),
+        CliCommand::Deploy { ref dir, commit } => deploy(dir, commit),
+        CliCommand::Doctor => doctor(),
+        CliCommand::Migrate { dry_run } => migrate(dry_run),
+        CliCommand::Render { ref path } => render(path),
+        CliCommand::Stats { json } => stats(json),
+        CliCommand::Serve {
+            host,
+            port,
+            open,
+            no_drafts,
+            future,
+        } => serve(host, port, open, !no_drafts, future),
         CliCommand::Theme { ref cmd } => theme(cmd),
     };
 
@@ -95,46 +246,188 @@ fn init(name: &str) -> Result<()> {
     Ok(())
 }
 
-fn new(path: &Path, tags: &[String]) -> Result<()> {
+fn new(path: &Path, tags: &[String], open: bool) -> Result<()> {
     let root_dir = env::current_dir()?;
-    let mut mb = Mdblog::new(&root_dir)?;
+    let mut mb = Mdblog::new(root_dir)?;
     mb.load_customize_settings()?;
     mb.create_post(path, tags)?;
+    if open {
+        open_editor(&mb.post_file_path(path)?);
+    }
     Ok(())
 }
 
-fn build() -> Result<()> {
+/// open `path` with `$VISUAL` or `$EDITOR`, only print the path if neither is set.
+fn open_editor(path: &Path) {
+    let editor = env::var("VISUAL")
+        .ok()
+        .filter(|s| !s.trim().is_empty())
+        .or_else(|| env::var("EDITOR").ok().filter(|s| !s.trim().is_empty()));
+    let editor = match editor {
+        Some(editor) => editor,
+        None => {
+            info!(path:% = path.display(); "no $VISUAL or $EDITOR set, created post: {}", path.display());
+            return;
+        }
+    };
+    let mut args = editor.split_whitespace();
+    let program = args.next().unwrap_or_default();
+    match Command::new(program).args(args).arg(path).status() {
+        Ok(status) if !status.success() => error!("editor {} exited with {}", editor, status),
+        Ok(_) => {}
+        Err(e) => {
+            error!(path:% = path.display(); "can not open editor {}: {}, created post: {}", editor, e, path.display())
+        }
+    }
+}
+
+fn build(args: BuildArgs) -> Result<()> {
     let root_dir = env::current_dir()?;
-    let mut mb = Mdblog::new(&root_dir)?;
+    let mut mb = Mdblog::new(root_dir)?;
     mb.load_customize_settings()?;
-    mb.build()?;
+    if !args.themes.is_empty() {
+        mb.set_build_themes(args.themes);
+    }
+    if args.no_theme_static {
+        mb.set_theme_static(false);
+    }
+    if let Some(jobs) = args.jobs {
+        mb.set_build_jobs(jobs);
+    }
+    if args.future {
+        mb.set_future(true);
+    }
+    if let Some(ref since) = args.since {
+        mb.set_build_since(since)?;
+    }
+    if args.prune {
+        mb.set_prune(true);
+    }
+    if args.fail_on_empty {
+        mb.set_fail_on_empty(true);
+    }
+    if args.maintenance {
+        mb.build_maintenance()?;
+    } else if let Some(ref post) = args.post {
+        mb.build_single_file(post)?;
+    } else if args.serve {
+        mb.set_open(args.open);
+        mb.build_and_serve(args.host, args.port, args.watch)?;
+    } else {
+        mb.build()?;
+        if args.timing {
+            print!("{}", mb.timing());
+        }
+    }
     Ok(())
 }
 
-fn serve(host: String, port: u16) -> Result<()> {
+fn check() -> Result<()> {
     let root_dir = env::current_dir()?;
-    let mut mb = Mdblog::new(&root_dir)?;
+    let mut mb = Mdblog::new(root_dir)?;
     mb.load_customize_settings()?;
-    mb.serve(host, port)?;
+    let problems = mb.validate()?;
+    for e in &problems {
+        log_error_chain(e);
+    }
+    if !problems.is_empty() {
+        return Err(mdblog::Error::ValidateFailed(problems.len()));
+    }
     Ok(())
 }
 
-fn theme(cmd: &ThemeCommand) -> Result<()> {
+fn deploy(dir: &Path, commit: bool) -> Result<()> {
+    let root_dir = env::current_dir()?;
+    let mut mb = Mdblog::new(root_dir)?;
+    mb.load_customize_settings()?;
+    mb.deploy(dir, commit)?;
+    Ok(())
+}
+
+fn doctor() -> Result<()> {
+    let root_dir = env::current_dir()?;
+    let mut mb = Mdblog::new(root_dir)?;
+    let doctor = mb.doctor();
+    print!("{}", doctor);
+    if doctor.failed() > 0 {
+        return Err(mdblog::Error::DoctorFailed(doctor.failed()));
+    }
+    Ok(())
+}
+
+fn migrate(dry_run: bool) -> Result<()> {
+    let root_dir = env::current_dir()?;
+    let mut mb = Mdblog::new(root_dir)?;
+    mb.load_customize_settings()?;
+    let migrated = mb.migrate(dry_run)?;
+    info!("{} posts to migrate", migrated.len());
+    Ok(())
+}
+
+fn render(path: &Path) -> Result<()> {
     let root_dir = env::current_dir()?;
     let mut mb = Mdblog::new(&root_dir)?;
+    // the default settings and the bundled `simple` theme are used outside of a blog directory
+    if root_dir.join("config.toml").is_file() {
+        mb.load_customize_settings()?;
+    }
+    let html = mb.render_file(path)?;
+    print!("{}", html);
+    Ok(())
+}
+
+fn stats(json: bool) -> Result<()> {
+    let root_dir = env::current_dir()?;
+    let mut mb = Mdblog::new(root_dir)?;
     mb.load_customize_settings()?;
+    let stats = mb.stats()?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&stats)?);
+    } else {
+        print!("{}", stats);
+    }
+    Ok(())
+}
+
+fn serve(host: String, port: u16, open: bool, drafts: bool, future: bool) -> Result<()> {
+    let root_dir = env::current_dir()?;
+    let mut mb = Mdblog::new(root_dir)?;
+    mb.load_customize_settings()?;
+    mb.set_drafts(drafts);
+    mb.set_future(future);
+    mb.set_open(open);
+    mb.serve(host, port)?;
+    Ok(())
+}
+
+fn theme(cmd: &ThemeCommand) -> Result<()> {
+    let load_blog = || -> Result<Mdblog> {
+        let root_dir = env::current_dir()?;
+        let mut mb = Mdblog::new(root_dir)?;
+        mb.load_customize_settings()?;
+        Ok(mb)
+    };
 
     match *cmd {
-        ThemeCommand::List => mb.list_blog_theme()?,
-        ThemeCommand::New { ref name } => mb.create_blog_theme(name)?,
-        ThemeCommand::Delete { ref name } => mb.delete_blog_theme(name)?,
-        ThemeCommand::Set { ref name } => mb.set_blog_theme(name)?,
+        ThemeCommand::List => load_blog()?.list_blog_theme()?,
+        ThemeCommand::New { ref name } => load_blog()?.create_blog_theme(name)?,
+        ThemeCommand::Delete { ref name } => load_blog()?.delete_blog_theme(name)?,
+        ThemeCommand::Set { ref name } => load_blog()?.set_blog_theme(name)?,
+        ThemeCommand::Install { ref path } => {
+            load_blog()?.install_blog_theme(path)?;
+        }
+        // exporting the bundled theme works outside of a blog directory
+        ThemeCommand::Export { ref dir } => mdblog::Theme::export_simple(dir)?,
     }
     Ok(())
 }
 
-fn log_error_chain(mut e: &dyn Error) {
-    error!("error: {}", e);
+fn log_error_chain(error: &mdblog::Error) {
+    match error.path() {
+        Some(path) => error!(path:% = path.display(); "error: {}", error),
+        None => error!("error: {}", error),
+    }
+    let mut e: &dyn Error = error;
     while let Some(source) = e.source() {
         error!("caused by: {}", source);
         e = source;
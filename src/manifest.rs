@@ -0,0 +1,26 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+/// the build manifest format version, bumped on incompatible format changes
+pub const MANIFEST_VERSION: u32 = 1;
+
+/// blog build manifest, exported as `manifest.json` of the build directory
+///
+/// deploy tools can diff it against the previous one to upload only the changed files.
+#[derive(Debug, Serialize)]
+pub struct Manifest {
+    /// manifest format version, see `MANIFEST_VERSION`
+    pub version: u32,
+    /// output files keyed by their `/` separated paths relative to the build directory
+    pub files: BTreeMap<String, ManifestEntry>,
+}
+
+/// build manifest entry of an output file
+#[derive(Debug, Serialize)]
+pub struct ManifestEntry {
+    /// FNV-1a hash of the file content, 8 hex digits
+    pub hash: String,
+    /// source file modified time in unix seconds, `null` if the file has no single source, e.g. index pages
+    pub source_mtime: Option<u64>,
+}
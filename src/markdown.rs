@@ -0,0 +1,40 @@
+use crate::settings::Settings;
+use crate::utils::markdown_to_html;
+
+/// markdown renderer
+///
+/// renders the markdown body of blog posts into html.
+pub trait MarkdownRenderer {
+    /// render markdown `content` into html.
+    fn render(&self, content: &str, settings: &Settings) -> String;
+}
+
+/// the default markdown renderer using `pulldown-cmark`
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PulldownCmark;
+
+impl MarkdownRenderer for PulldownCmark {
+    fn render(&self, content: &str, settings: &Settings) -> String {
+        markdown_to_html(content, settings)
+    }
+}
+
+/// render markdown `content` into html as the blog posts are rendered with the default settings,
+/// tables, footnotes, task lists and strikethrough are enabled.
+pub fn render_markdown(content: &str) -> String {
+    markdown_to_html(content, &Settings::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_markdown_extensions() {
+        let html = render_markdown("**bold** ~~gone~~\n\n| a |\n|---|\n| 1 |\n\n- [x] done\n");
+        assert!(html.contains("<strong>bold</strong>"), "{}", html);
+        assert!(html.contains("<del>gone</del>"), "{}", html);
+        assert!(html.contains("<table>"), "{}", html);
+        assert!(html.contains("checkbox"), "{}", html);
+    }
+}
@@ -12,6 +12,8 @@ pub struct Page {
     pub index: usize,
     /// page index name
     pub name: String,
+    /// page url, relative to the site root, see `Settings::trailing_slash`
+    pub url: String,
     /// page posts array
     pub posts: Vec<Rc<Post>>
 }
@@ -1,25 +1,50 @@
-use std::fs::File;
-use std::io::Read;
+use std::collections::HashMap;
+use std::io;
 use std::path::{Path, PathBuf};
 
-use time::OffsetDateTime;
-use log::debug;
+use time::{OffsetDateTime, UtcOffset};
+use log::{debug, warn};
+use serde::ser::{SerializeMap, Serializer};
 use serde::{Deserialize, Serialize};
 
 use crate::error::{Error, Result};
-use crate::utils::markdown_to_html;
+use crate::markdown::{MarkdownRenderer, PulldownCmark};
+use crate::settings::Settings;
+use crate::utils::{
+    find_excerpt_separator, format_utc_offset, html_to_text, markdown_inline_to_html, normalize_trailing_slash,
+    parse_utc_offset, read_file, sanitize_html, split_blank_line_headers, split_date_prefix, split_fenced_headers,
+    trim_leading_blank_lines, url_dest,
+};
 
 /// blog post headers
 ///
 /// the blog post headers is parsed using yaml format.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PostHeaders {
-    /// post created local time, `created: 1970-01-01T00:00:00+08:00`
+    /// post created local time, `created: 1970-01-01T00:00:00+08:00`,
+    /// defaults to the `YYYY-MM-DD-` prefix of the post filename
     #[serde(with = "time::serde::rfc3339")]
     pub created: OffsetDateTime,
-    /// post hidden flag, `hidden: true`, default `false`
+    /// post updated local time, `updated: 1970-01-01T00:00:00+08:00`, default none
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub updated: Option<OffsetDateTime>,
+    /// post hidden flag, hidden posts are only built by the dev server,
+    /// `hidden: true` or its alias `draft: true`, default `false`
     #[serde(default)]
     pub hidden: bool,
+    /// post type, `type: page` for a static page excluded from index, feed and tags,
+    /// defaults to `page` for the files of `pages` directory, otherwise empty for a post
+    #[serde(default, rename = "type")]
+    pub kind: String,
+    /// post manual order weight, lighter posts come first with `sort_order = "weight"`, `weight: 10`, default none
+    #[serde(default)]
+    pub weight: Option<i64>,
+    /// post featured flag, featured posts are pinned to the top of index pages, `featured: true`, default `false`
+    #[serde(default)]
+    pub featured: bool,
+    /// post empty body allowed flag, `allow_empty_body: true`, default `false`
+    #[serde(default)]
+    pub allow_empty_body: bool,
     /// post tags, `tags: [hello, world]`, default `[]`
     #[serde(default)]
     pub tags: Vec<String>,
@@ -29,12 +54,69 @@ pub struct PostHeaders {
     /// post title
     #[serde(default)]
     pub title: String,
+    /// post old urls redirected to the post, `aliases: [/old-path.html]`, default `[]`,
+    /// an alias must stay inside the build directory and not replace another generated page
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// the external url of a link post, its page redirects to the url, `redirect_to: https://example.com/article`
+    #[serde(default)]
+    pub redirect_to: String,
+    /// post extra stylesheets, `css: [/media/chart.css]`, default `[]`
+    #[serde(default)]
+    pub css: Vec<String>,
+    /// post extra scripts, `js: [/media/chart.js]`, default `[]`
+    #[serde(default)]
+    pub js: Vec<String>,
+    /// custom headers not known by mdblog, used by themes as `post.headers.extra.xxx`,
+    /// rejected if `Settings::deny_unknown_fields`
+    #[serde(flatten, serialize_with = "serialize_extra")]
+    pub extra: HashMap<String, serde_yaml::Value>,
+}
+
+impl PostHeaders {
+    /// parse the yaml post headers `head` without `---` fences, e.g. for editor integrations.
+    ///
+    /// `draft` is accepted as an alias of `hidden`, missing required headers like `created` are errors.
+    pub fn parse(head: &str) -> Result<PostHeaders> {
+        let value = Self::parse_value(head).map_err(Error::HeadersParse)?;
+        serde_yaml::from_value(value).map_err(Error::HeadersParse)
+    }
+
+    /// parse the yaml post headers `head` into a yaml value, `draft` is renamed to `hidden`.
+    fn parse_value(head: &str) -> serde_yaml::Result<serde_yaml::Value> {
+        let mut value: serde_yaml::Value = serde_yaml::from_str(head)?;
+        if let Some(map) = value.as_mapping_mut() {
+            // `draft` is an alias of `hidden`
+            if let Some(draft) = map.remove("draft") {
+                match map.get("hidden") {
+                    Some(hidden) if *hidden != draft => {
+                        return Err(serde::de::Error::custom("conflicting `draft` and `hidden` headers"));
+                    }
+                    _ => {
+                        map.insert("hidden".into(), draft);
+                    }
+                }
+            }
+        }
+        Ok(value)
+    }
+}
+
+/// serialize the flattened custom headers under the `extra` key.
+fn serialize_extra<S>(extra: &HashMap<String, serde_yaml::Value>, serializer: S) -> std::result::Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let mut map = serializer.serialize_map(Some(1))?;
+    map.serialize_entry("extra", extra)?;
+    map.end()
 }
 
 /// blog post
 ///
 /// every blog post is composed of `head` part and `body` part.
-/// the two part is separated by the first blank line.
+/// the two part is separated by the first blank line,
+/// or the `head` part is fenced by `---` lines.
 #[derive(Serialize)]
 pub struct Post {
     /// blog root path
@@ -49,28 +131,61 @@ pub struct Post {
     pub url: PathBuf,
     /// post headers
     pub headers: PostHeaders,
+    /// the explicit `description` header rendered as inline markdown, empty if the description is generated
+    pub description_html: String,
     /// post html body
     pub content: String,
+    /// post plain text body, html tags are stripped
+    pub content_text: String,
+    /// post html excerpt before the excerpt separator, empty if the post has no separator
+    pub excerpt: String,
+    /// the number of words in post markdown body
+    pub word_count: usize,
 }
 
 impl Post {
-    /// create new `Post`
+    /// create new `Post` with the default settings and markdown renderer.
     pub fn new<P: AsRef<Path>>(root: P, path: P) -> Result<Post> {
+        Self::with_settings(root, path, &Settings::default(), &PulldownCmark)
+    }
+
+    /// create new `Post` by the blog `settings`, the markdown body is rendered by `renderer`.
+    pub fn with_settings<P: AsRef<Path>>(
+        root: P,
+        path: P,
+        settings: &Settings,
+        renderer: &dyn MarkdownRenderer,
+    ) -> Result<Post> {
         let root = root.as_ref();
         let path = path.as_ref();
-        debug!("loading post: {}", path.display());
+        debug!(path:% = path.display(); "loading post: {}", path.display());
+
+        let (headers, description_html, content, excerpt, word_count) =
+            Self::split_file(root, path, settings, renderer)?;
+
+        // `posts/hello.md` is linked as `/2021/hello.html` with `year_directories`
+        let url_path = if settings.year_directories && headers.kind != "page" {
+            let rel_path = path.strip_prefix("posts").unwrap_or(path);
+            Path::new(&headers.created.year().to_string()).join(rel_path)
+        } else {
+            path.to_owned()
+        };
 
-        let (headers, content) = Self::split_file(root, path)?;
+        // `posts/my-post/index.md` is named after its directory `my-post`
+        let is_index = path.file_stem().map(|s| s == "index").unwrap_or(false);
+        let index_dir = url_path.parent().filter(|p| is_index && p.file_name().is_some());
 
         let mut title = if headers.title.is_empty() {
-            path.file_stem()
+            let name = index_dir
+                .map_or(path.file_stem(), |dir| dir.file_name())
                 .and_then(|x| x.to_str())
-                .expect(&format!("post filename format error: {}", path.display()))
+                .expect(&format!("post filename format error: {}", path.display()));
+            split_date_prefix(name).map_or(name, |(_, rest)| rest)
         } else {
             headers.title.as_ref()
         };
 
-        let title_no_underscore_binding = title.replace("_", " ");
+        let title_no_underscore_binding = title.replace('_', " ");
         title = &title_no_underscore_binding;
 
         // Capitalize the first letter of each word
@@ -87,10 +202,20 @@ impl Post {
             .join(" ");
         title = title_capitalized_binding;
 
-        let url = Path::new("/").join(path).with_extension("html");
+        let url = match index_dir {
+            // `posts/2021-01-01-my-post/index.md` is linked as `/posts/my-post/`
+            Some(dir) => {
+                let name = dir.file_name().and_then(|s| s.to_str()).unwrap_or_default();
+                let name = split_date_prefix(name).map_or(name, |(_, rest)| rest);
+                Path::new("/").join(dir.with_file_name(name)).join("")
+            }
+            None => Path::new("/").join(Self::undated_path(&url_path)).with_extension("html"),
+        };
 
         // Replace backslashes with slashes for Windows
-        let formatted_path = url.to_string_lossy().replace("\\", "/");
+        let formatted_path = url.to_string_lossy().replace('\\', "/");
+
+        let content_text = html_to_text(&content);
 
         Ok(Post {
             root: root.to_owned(),
@@ -99,59 +224,162 @@ impl Post {
             title: title.to_owned(),
             url,
             headers,
+            description_html,
             content,
+            content_text,
+            excerpt,
+            word_count,
         })
     }
 
-    /// split a post into `headers` and `content`
-    fn split_file(root: &Path, path: &Path) -> Result<(PostHeaders, String)> {
+    /// split a post into `headers`, `description_html`, `content`, `excerpt` and body word count
+    fn split_file(
+        root: &Path,
+        path: &Path,
+        settings: &Settings,
+        renderer: &dyn MarkdownRenderer,
+    ) -> Result<(PostHeaders, String, String, String, usize)> {
         let fp = root.join(path);
-        let mut fo = File::open(fp)?;
-        let mut content = String::new();
-        fo.read_to_string(&mut content)?;
-
-        // Get the line ending format
-        let mut line_ending = "\n\n";
-        if content.find("\r\n").is_some() {
-            line_ending = "\r\n";
-        }
+        let mut buf = Vec::new();
+        read_file(&fp, &mut buf)?;
+        let content = String::from_utf8(buf)
+            .map_err(|e| Error::FileRead(fp, io::Error::new(io::ErrorKind::InvalidData, e)))?;
 
-        let v: Vec<&str> = content.splitn(2, line_ending).collect();
+        // headers are fenced by `---` lines, or separated from body by the first blank line
+        let content = trim_leading_blank_lines(&content);
+        let parts = split_fenced_headers(content).or_else(|| split_blank_line_headers(content));
 
-        if v.len() != 2 {
-            return Err(Error::PostOnlyOnePart(path.into()));
-        }
-        let head = v[0].trim();
-        let body = v[1].trim();
+        // a post of only one part is all headers, allowed with `allow_empty_body: true`
+        let one_part = parts.is_none();
+        let (head, body) = parts.unwrap_or((content, ""));
+        let head = head.trim();
+        let body = body.trim();
         if head.is_empty() {
+            if one_part {
+                return Err(Error::PostOnlyOnePart(path.into()));
+            }
             return Err(Error::PostNoHead(path.into()));
         }
-        if body.is_empty() {
-            return Err(Error::PostNoBody(path.into()));
-        }
-        let mut headers: PostHeaders = match serde_yaml::from_str(head) {
+        let mut headers: PostHeaders = match Self::parse_headers(head, path, settings) {
             Ok(headers) => headers,
             Err(e) => {
                 return Err(Error::PostHeadPaser(e, path.into()));
             }
         };
+        if settings.deny_unknown_fields {
+            if let Some(key) = headers.extra.keys().min() {
+                return Err(Error::PostHeaderUnknown(path.into(), key.clone()));
+            }
+        }
+        if body.is_empty() && !headers.allow_empty_body {
+            if one_part {
+                return Err(Error::PostOnlyOnePart(path.into()));
+            }
+            return Err(Error::PostNoBody(path.into()));
+        }
+        // tags are trimmed and deduplicated case-insensitively, keeping the first occurrence, blank tags are dropped
+        let mut tags: Vec<String> = Vec::with_capacity(headers.tags.len());
+        for tag in &headers.tags {
+            let tag = tag.trim();
+            if tag.is_empty() {
+                continue;
+            }
+            if tags.iter().any(|t| t.to_lowercase() == tag.to_lowercase()) {
+                warn!(path:% = path.display(); "post {} has duplicate tag {:?}, collapsed", path.display(), tag);
+                continue;
+            }
+            tags.push(tag.to_string());
+        }
+        headers.tags = tags;
+
+        let separator = find_excerpt_separator(body, &settings.excerpt_separator);
+        let description_html = markdown_inline_to_html(&headers.description);
         if headers.description.is_empty() {
-            let desc = body
-                .split("\n\n")
-                .take(1)
-                .next()
-                .unwrap_or("")
-                .split_whitespace()
-                .take(100)
-                .collect::<Vec<_>>()
-                .join(" ");
+            let desc = match separator {
+                Some((start, _)) => body[..start].split_whitespace().collect::<Vec<_>>().join(" "),
+                None => body
+                    .split("\n\n")
+                    .take(1)
+                    .next()
+                    .unwrap_or("")
+                    .split_whitespace()
+                    .take(100)
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            };
             headers.description.push_str(&desc);
             if !headers.description.is_empty() {
                 headers.description.push_str("...");
             }
         }
-        let content = markdown_to_html(body);
-        Ok((headers, content))
+        let word_count = body.split_whitespace().count();
+        // the body of `.html` posts is html already
+        let is_html = path.extension().map_or(false, |ext| ext == "html");
+        let render = |body: &str| {
+            if is_html {
+                body.to_string()
+            } else {
+                renderer.render(body, settings)
+            }
+        };
+        let (content, excerpt) = match separator {
+            Some((start, end)) => (
+                render(&format!("{}{}", &body[..start], &body[end..])),
+                render(&body[..start]),
+            ),
+            None => (render(body), String::new()),
+        };
+        let (description_html, content, excerpt) = if settings.sanitize_html {
+            (sanitize_html(&description_html), sanitize_html(&content), sanitize_html(&excerpt))
+        } else {
+            (description_html, content, excerpt)
+        };
+        Ok((headers, description_html, content, excerpt, word_count))
+    }
+
+    /// parse post headers like `PostHeaders::parse`, `created` falls back to the date prefix of the post filename.
+    fn parse_headers(head: &str, path: &Path, settings: &Settings) -> serde_yaml::Result<PostHeaders> {
+        let mut value = PostHeaders::parse_value(head)?;
+        // the files of `pages` directory are static pages
+        if let Some(map) = value.as_mapping_mut() {
+            if path.starts_with("pages") && !map.contains_key("type") {
+                map.insert("type".into(), "page".into());
+            }
+        }
+        let date = path
+            .file_stem()
+            .filter(|stem| *stem != "index")
+            .or_else(|| path.parent().and_then(|dir| dir.file_name()))
+            .and_then(|name| name.to_str())
+            .and_then(split_date_prefix)
+            .map(|(date, _)| date);
+        if let (Some(date), Some(map)) = (date, value.as_mapping_mut()) {
+            if !map.contains_key("created") {
+                let offset = match settings.timezone.as_str() {
+                    "" | "local" => UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC),
+                    tz => parse_utc_offset(tz).unwrap_or(UtcOffset::UTC),
+                };
+                let created = format!("{}T00:00:00{}", date, format_utc_offset(offset));
+                map.insert("created".into(), created.into());
+            }
+        }
+        serde_yaml::from_value(value)
+    }
+
+    /// the post path with the `YYYY-MM-DD-` filename date prefix removed.
+    fn undated_path(path: &Path) -> PathBuf {
+        let stem = path.file_stem().and_then(|s| s.to_str()).and_then(split_date_prefix);
+        match (stem, path.extension()) {
+            (Some((_, rest)), Some(ext)) => path.with_file_name(rest).with_extension(ext),
+            (Some((_, rest)), None) => path.with_file_name(rest),
+            _ => path.to_owned(),
+        }
+    }
+
+    /// normalize the trailing slash of post url, see `Settings::trailing_slash`.
+    pub fn normalize_url(&mut self, trailing_slash: &str) {
+        self.formatted_path = normalize_trailing_slash(&self.formatted_path, trailing_slash);
+        self.url = PathBuf::from(normalize_trailing_slash(&self.url.to_string_lossy(), trailing_slash));
     }
 
     /// the absolute path of blog post markdown file.
@@ -159,8 +387,61 @@ impl Post {
         self.root.join(&self.path)
     }
 
-    /// the absolute path of blog post html file.
+    /// check the post is a static page, see `PostHeaders::kind`.
+    pub fn is_page(&self) -> bool {
+        self.headers.kind == "page"
+    }
+
+    /// the path of blog post html file relative to the build directory.
+    ///
+    /// it is the single source of truth of the output location, always consistent with the post `url`,
+    /// a directory url like `/posts/my-post/` or `/posts/my-post` is written as its `index.html`.
     pub fn dest(&self) -> PathBuf {
-        self.path.with_extension("html")
+        url_dest(&self.formatted_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split_fixture(content: &str) -> Result<(PostHeaders, String, String, String, usize)> {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(root.path().join("posts")).unwrap();
+        std::fs::write(root.path().join("posts/hello.md"), content).unwrap();
+        Post::split_file(root.path(), Path::new("posts/hello.md"), &Settings::default(), &PulldownCmark)
+    }
+
+    #[test]
+    fn folder_post_url_without_date_prefix() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(root.path().join("posts/2021-01-01-my-post")).unwrap();
+        std::fs::write(root.path().join("posts/2021-01-01-my-post/index.md"), "---\ntags: []\n---\nhello\n").unwrap();
+        let post = Post::new(root.path(), Path::new("posts/2021-01-01-my-post/index.md")).unwrap();
+        assert_eq!(post.formatted_path, "/posts/my-post/");
+        assert_eq!(post.title, "My-post");
+        assert_eq!(post.headers.created.date().to_string(), "2021-01-01");
+    }
+
+    #[test]
+    fn split_file_with_leading_blank_line_before_fenced_headers() {
+        let (headers, _, content, ..) =
+            split_fixture("\n---\ncreated: 2021-01-01T00:00:00Z\ntags: [hello]\n---\n\nhello world\n").unwrap();
+        assert_eq!(headers.tags, ["hello"]);
+        assert!(content.contains("hello world"), "{}", content);
+    }
+
+    #[test]
+    fn split_file_drops_blank_tags() {
+        let (headers, ..) = split_fixture("---\ncreated: 2021-01-01T00:00:00Z\ntags: [\" \", rust, \"\"]\n---\nhello\n").unwrap();
+        assert_eq!(headers.tags, ["rust"]);
+    }
+
+    #[test]
+    fn split_file_with_leading_blank_line_before_blank_line_headers() {
+        let (headers, _, content, ..) =
+            split_fixture("\n\ncreated: 2021-01-01T00:00:00Z\ntags: [hello]\n\nhello world\n").unwrap();
+        assert_eq!(headers.tags, ["hello"]);
+        assert!(content.contains("hello world"), "{}", content);
     }
 }
@@ -0,0 +1,11 @@
+//! the commonly used types of the stable public api, `use mdblog::prelude::*;`.
+//!
+//! the types re-exported here follow semver, other public items may change in minor versions.
+
+pub use crate::error::{Error, Result};
+pub use crate::markdown::{render_markdown, MarkdownRenderer, PulldownCmark};
+pub use crate::post::{Post, PostHeaders};
+pub use crate::settings::Settings;
+pub use crate::tag::Tag;
+pub use crate::theme::Theme;
+pub use crate::Mdblog;
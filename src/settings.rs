@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use config::{ConfigError, Source, Value};
 use serde::{Deserialize, Serialize};
@@ -8,12 +8,22 @@ use serde::{Deserialize, Serialize};
 pub struct Settings {
     /// blog base url
     pub site_url: String,
+    /// blog subpath when hosted under a subdirectory, e.g. `/~user/blog`, appended to `site_url`
+    pub base_path: String,
     /// blog site name
     pub site_name: String,
     /// blog site motto
     pub site_motto: String,
     /// blog footer note
     pub footer_note: String,
+    /// markdown note rendered below every post body, e.g. a license note, empty to render nothing
+    pub post_footer: String,
+    /// html page title format, `{page}` is the page title and `{site}` is the `site_name`, e.g. `{page} - {site}`
+    pub title_format: String,
+    /// blog author
+    pub author: String,
+    /// blog timezone of generated timestamps, `local`, `UTC` or a fixed offset like `+08:00`, default local
+    pub timezone: String,
     /// blog media directory
     pub media_dir: String,
     /// blog build root directory
@@ -22,25 +32,155 @@ pub struct Settings {
     pub theme: String,
     /// blog theme root directory
     pub theme_root_dir: String,
+    /// blog theme directory, absolute or relative to the blog root, empty for `<theme_root_dir>/<theme>`
+    pub theme_dir: String,
+    /// export theme static files, disable it if they are served from elsewhere, e.g. a CDN
+    pub theme_static: bool,
+    /// build subdirectory of theme static files, e.g. `assets`, empty for the build directory itself
+    pub static_dir: String,
+    /// blog themes of multiple themes build, every theme is built into `build_dir/<theme>`
+    pub themes: Vec<String>,
+    /// add content hash into theme css/js paths, `filename`, `query` or empty to disable
+    pub cache_bust: String,
+    /// concatenate the theme css files into `static/all.css` and the js files into `static/all.js`
+    pub bundle_assets: bool,
+    /// theme static files bundled first in the order, e.g. `["static/reset.css", "static/main.css"]`
+    pub bundle_order: Vec<String>,
+    /// the max width of page content of the simple theme, a css length, e.g. `960px`
+    pub max_content_width: String,
+    /// inline theme `main.css` into html pages instead of linking it
+    pub inline_css: bool,
+    /// prepend the post source path as an html comment to rendered posts
+    pub debug_comments: bool,
+    /// number of parallel build jobs, `0` for the number of CPUs, `1` builds serially
+    pub jobs: usize,
     /// blog rebuild interval
     pub rebuild_interval: u8,
-    /// post count per index page
+    /// post count per index page, `0` puts all posts on one page
     pub posts_per_page: usize,
+    /// the number of posts per tag page, `0` uses `posts_per_page`
+    pub posts_per_tag_page: usize,
+    /// index and tag pages post order, `date_desc`, `date_asc`, `title` or `weight`
+    pub sort_order: String,
+    /// index pages directory relative to the build directory, empty for the root
+    pub index_path: String,
+    /// build posts into year directories, `posts/hello.md` is built into `<year>/hello.html` by its `created` year
+    pub year_directories: bool,
+    /// post excerpt separator, matched case-insensitively with optional whitespace inside
+    pub excerpt_separator: String,
+    /// the link text after post excerpts on index pages
+    pub read_more_text: String,
+    /// render the full post content on index and tag pages instead of excerpts
+    pub index_full_content: bool,
+    /// shift rendered markdown heading levels, `1` renders `#` as `<h2>`
+    pub heading_offset: u8,
+    /// give rendered headings an `id` anchor of their text slug, e.g. `<h2 id="getting-started">`
+    pub heading_anchors: bool,
+    /// render straight quotes, dashes and ellipses as typographic punctuation
+    pub smart_punctuation: bool,
+    /// add `loading="lazy"` to markdown images
+    pub image_lazy_loading: bool,
+    /// the `class` attribute of markdown images, empty to add none
+    pub image_class: String,
+    /// wrap code blocks in `<div class="code-block">` and add theme copy buttons
+    pub code_copy_button: bool,
+    /// keep `$...$` and `$$...$$` math untouched by markdown for client-side KaTeX or MathJax
+    pub math: bool,
+    /// sanitize the rendered post html by an allowlist, e.g. for a multi-author blog
+    pub sanitize_html: bool,
+    /// emit JSON-LD structured data in post pages
+    pub json_ld: bool,
+    /// post file extensions treated as markdown
+    pub markdown_extensions: Vec<String>,
+    /// reject post headers not known by mdblog instead of collecting them as `extra` headers
+    pub deny_unknown_fields: bool,
+    /// internal link trailing slash of posts, index and tag pages,
+    /// `always` links `/posts/hello.html` as `/posts/hello/`, `never` as `/posts/hello`, empty keeps the links
+    pub trailing_slash: String,
+    /// ask search engines not to index any page, e.g. for a staging site
+    pub noindex: bool,
+    /// export `manifest.json` of output file content hashes and source modified times for deploy tools
+    pub build_manifest: bool,
+    /// export the `_headers` file of `Cache-Control` hints for Netlify or Cloudflare Pages
+    pub headers_file: bool,
+    /// export `latest/index.html` redirecting to the newest post, e.g. for "read my newest post" links
+    pub latest_page: bool,
+    /// warn about post links to the `site_url` host with a different scheme or `www.` prefix
+    pub canonical_host_check: bool,
+    /// fail the build on `post:<slug>` links matching no post, they are only warned by default
+    pub strict_post_links: bool,
+    /// extra files copied verbatim into the build directory, e.g. `humans.txt`
+    pub extra_files: Vec<ExtraFile>,
+    /// site-wide theme constants, e.g. social handles, available in templates as `globals.xxx`,
+    /// the keys are lowercased by the config loader
+    pub globals: BTreeMap<String, serde_json::Value>,
+}
+
+/// extra file copied verbatim into the build directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtraFile {
+    /// source file path, relative to the blog root directory
+    pub src: String,
+    /// destination file path, relative to the build directory
+    pub dest: String,
 }
 
 impl Default for Settings {
     fn default() -> Self {
         return Settings {
             site_url: String::from(""),
+            base_path: String::from(""),
             site_name: String::from("Mdblog"),
             site_motto: String::from("Simple is Beautiful!"),
             footer_note: String::from("Keep It Simple, Stupid!"),
+            post_footer: String::from(""),
+            title_format: String::from("{page}"),
+            author: String::from(""),
+            timezone: String::from("local"),
             media_dir: String::from("media"),
             build_dir: String::from("_build"),
             theme: String::from("simple"),
             theme_root_dir: String::from("_themes"),
+            theme_dir: String::from(""),
+            theme_static: true,
+            static_dir: String::from("static"),
+            themes: Vec::new(),
+            cache_bust: String::from(""),
+            bundle_assets: false,
+            bundle_order: Vec::new(),
+            max_content_width: String::from("1440px"),
+            inline_css: false,
+            debug_comments: false,
+            jobs: 0,
             rebuild_interval: 2,
             posts_per_page: 20,
+            posts_per_tag_page: 0,
+            sort_order: String::from("date_desc"),
+            index_path: String::from(""),
+            year_directories: false,
+            excerpt_separator: String::from("<!-- more -->"),
+            read_more_text: String::from("Read more"),
+            index_full_content: false,
+            heading_offset: 0,
+            heading_anchors: false,
+            smart_punctuation: false,
+            image_lazy_loading: true,
+            image_class: String::from(""),
+            code_copy_button: false,
+            math: false,
+            sanitize_html: false,
+            json_ld: false,
+            markdown_extensions: vec![String::from("md"), String::from("markdown"), String::from("mkd")],
+            deny_unknown_fields: false,
+            trailing_slash: String::from(""),
+            noindex: false,
+            build_manifest: false,
+            headers_file: false,
+            latest_page: false,
+            canonical_host_check: false,
+            strict_post_links: false,
+            extra_files: Vec::new(),
+            globals: BTreeMap::new(),
         };
     }
 }
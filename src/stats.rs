@@ -0,0 +1,41 @@
+use std::fmt;
+
+use serde::Serialize;
+
+/// blog content statistics
+#[derive(Debug, Serialize)]
+pub struct Stats {
+    /// the number of posts
+    pub posts: usize,
+    /// the number of words across all posts
+    pub words: usize,
+    /// the average number of words per post
+    pub average_words: usize,
+    /// tag names and their post numbers, most frequent first
+    pub tags: Vec<(String, usize)>,
+    /// the earliest post created time
+    pub first_created: Option<String>,
+    /// the latest post created time
+    pub last_created: Option<String>,
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{:<16}{}", "posts:", self.posts)?;
+        writeln!(f, "{:<16}{}", "words:", self.words)?;
+        writeln!(f, "{:<16}{}", "average words:", self.average_words)?;
+        writeln!(
+            f,
+            "{:<16}{} ~ {}",
+            "created:",
+            self.first_created.as_deref().unwrap_or("-"),
+            self.last_created.as_deref().unwrap_or("-")
+        )?;
+        writeln!(f, "{:<16}{}", "tags:", self.tags.len())?;
+        let width = self.tags.iter().map(|(name, _)| name.chars().count()).max().unwrap_or(0);
+        for (name, num) in &self.tags {
+            writeln!(f, "  {:<width$}  {}", name, num, width = width)?;
+        }
+        Ok(())
+    }
+}
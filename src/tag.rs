@@ -1,17 +1,37 @@
 use std::rc::Rc;
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use crate::post::Post;
+use crate::utils::slugify;
+
+/// blog tag metadata, loaded from the `tags.toml` file of blog root directory
+///
+/// ```toml
+/// [rust]
+/// description = "posts about the rust language"
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TagInfo {
+    /// tag description
+    #[serde(default)]
+    pub description: String,
+}
 
 /// blog tag
 #[derive(Serialize)]
 pub struct Tag {
     /// tag name
     pub name: String,
+    /// tag slug
+    pub slug: String,
+    /// the url of the first tag page, relative to the site root
+    pub url: String,
+    /// tag description, see `TagInfo`
+    pub description: String,
     /// the number of tag posts
     pub num: isize,
-    /// the posts
+    /// the posts, newest first, hidden posts are excluded
     pub posts: Vec<Rc<Post>>,
 }
 
@@ -20,6 +40,9 @@ impl Tag {
     pub fn new(name: &str) -> Tag {
         Tag {
             name: name.to_string(),
+            slug: slugify(name),
+            url: String::new(),
+            description: String::new(),
             num: 0,
             posts: Vec::new(),
         }
@@ -1,10 +1,13 @@
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 
-use log::{debug, info};
+use log::{debug, info, warn};
 use tera::Tera;
+use walkdir::WalkDir;
 
 use crate::error::{Error, Result};
-use crate::utils::{read_file, write_file};
+use crate::settings::Settings;
+use crate::utils::{hashed_file_name, hashed_query, read_file, write_file, write_shared_file};
 
 macro_rules! try_init_template {
     ($render:expr, $tpl_name:expr, $tpl_str:expr) => {
@@ -16,6 +19,16 @@ macro_rules! try_init_template {
     };
 }
 
+macro_rules! try_init_optional_template {
+    ($render:expr, $tpl_name:expr, $tpl_str:expr) => {
+        if $tpl_str.is_empty() {
+            debug!("theme template {} not found, skip it", $tpl_name);
+        } else {
+            try_init_template!($render, $tpl_name, $tpl_str);
+        }
+    };
+}
+
 macro_rules! try_read_file {
     ($src_dir: expr, $p: expr, $buf: expr) => {
         let p = $src_dir.join($p);
@@ -26,10 +39,10 @@ macro_rules! try_read_file {
 }
 
 macro_rules! try_write_file {
-    ($src_dir: expr, $dest_dir: expr, $p: expr, $buf: expr) => {
+    ($src_dir: expr, $dest_dir: expr, $p: expr, $dest: expr, $buf: expr, $shared: expr) => {
         let p = $src_dir.join($p);
         if p.exists() {
-            write_file(&$dest_dir.join($p), $buf)?;
+            write_shared_file(&$dest_dir.join($dest), $buf, $shared)?;
         }
     };
 }
@@ -41,9 +54,12 @@ pub struct Theme {
     pub root: PathBuf,
     /// theme name
     pub name: String,
+    /// theme source directory
+    pub dir: PathBuf,
     /// theme renderer
     pub renderer: Tera,
     main_css: Vec<u8>,
+    main_dark_css: Vec<u8>,
     main_js: Vec<u8>,
 
     main_wasm: Vec<u8>,
@@ -53,27 +69,44 @@ pub struct Theme {
     base: Vec<u8>,
     index: Vec<u8>,
     post: Vec<u8>,
+    page: Vec<u8>,
+    maintenance: Vec<u8>,
     tag: Vec<u8>,
     tags: Vec<u8>,
+
+    /// concatenated static files by bundle name, see `Theme::bundle_assets`
+    bundles: BTreeMap<String, Vec<u8>>,
+    /// the bundle name of every bundled static file
+    bundled: BTreeMap<String, String>,
 }
 
 impl Theme {
-    /// create new `Theme`
+    /// create new `Theme` of the `name` directory under `root`.
     pub fn new<P: AsRef<Path>>(root: P, name: &str) -> Result<Theme> {
+        let root = root.as_ref();
+        Theme::from_dir(root, name, root.join(name))
+    }
+
+    /// create new `Theme` from the theme directory `dir`, independent of the theme `name`.
+    ///
+    /// the bundled `simple` theme is used if `dir` is absent and `name` is `simple`.
+    pub fn from_dir<P: AsRef<Path>, Q: AsRef<Path>>(root: P, name: &str, dir: Q) -> Result<Theme> {
         debug!("loading theme: {}", &name);
         let root = root.as_ref();
+        let src_dir = dir.as_ref();
         let mut theme = Theme {
             root: root.to_owned(),
             name: name.to_string(),
+            dir: src_dir.to_owned(),
             renderer: Tera::default(),
             ..Default::default()
         };
-        let src_dir = root.join(name);
         if !src_dir.exists() {
             if name != "simple" {
                 return Err(Error::ThemeNotFound(name.into()));
             }
             theme.main_css.extend_from_slice(SIMPLE_MAIN_CSS);
+            theme.main_dark_css.extend_from_slice(SIMPLE_MAIN_DARK_CSS);
             theme.main_js.extend_from_slice(SIMPLE_MAIN_JS);
 
             theme.main_wasm.extend_from_slice(SIMPLE_MAIN_WASM);
@@ -83,6 +116,8 @@ impl Theme {
             theme.base.extend_from_slice(SIMPLE_BASE);
             theme.index.extend_from_slice(SIMPLE_INDEX);
             theme.post.extend_from_slice(SIMPLE_POST);
+            theme.page.extend_from_slice(SIMPLE_PAGE);
+            theme.maintenance.extend_from_slice(SIMPLE_MAINTENANCE);
             theme.tag.extend_from_slice(SIMPLE_TAG);
             theme.tags.extend_from_slice(SIMPLE_TAGS);
             theme.init_template()?;
@@ -90,32 +125,55 @@ impl Theme {
         }
 
         try_read_file!(src_dir, "static/main.css", &mut theme.main_css);
+        try_read_file!(src_dir, "static/main-dark.css", &mut theme.main_dark_css);
         try_read_file!(src_dir, "static/bundle.js", &mut theme.main_js);
 
         try_read_file!(src_dir, "static/pkg/blog_wasm_bg.wasm", &mut theme.main_wasm);
         try_read_file!(src_dir, "static/pkg/blog_wasm_bg.js", &mut theme.main_wasm_bg);
 
-        read_file(&src_dir.join("templates/atom.tpl"), &mut theme.atom)?;
         read_file(&src_dir.join("templates/base.tpl"), &mut theme.base)?;
         read_file(&src_dir.join("templates/index.tpl"), &mut theme.index)?;
         read_file(&src_dir.join("templates/post.tpl"), &mut theme.post)?;
-        read_file(&src_dir.join("templates/tag.tpl"), &mut theme.tag)?;
-        read_file(&src_dir.join("templates/tags.tpl"), &mut theme.tags)?;
+        try_read_file!(src_dir, "templates/atom.tpl", &mut theme.atom);
+        try_read_file!(src_dir, "templates/page.tpl", &mut theme.page);
+        try_read_file!(src_dir, "templates/maintenance.tpl", &mut theme.maintenance);
+        // the standalone maintenance page of `simple` theme works for any theme
+        if theme.maintenance.is_empty() {
+            theme.maintenance.extend_from_slice(SIMPLE_MAINTENANCE);
+        }
+        try_read_file!(src_dir, "templates/tag.tpl", &mut theme.tag);
+        try_read_file!(src_dir, "templates/tags.tpl", &mut theme.tags);
         theme.init_template()?;
         return Ok(theme);
     }
 
+    /// reload theme templates and static files from the theme directory.
+    pub fn reload(&mut self) -> Result<()> {
+        *self = Theme::from_dir(&self.root, &self.name, &self.dir)?;
+        Ok(())
+    }
+
     /// init renderer template.
+    ///
+    /// `base.tpl`, `index.tpl` and `post.tpl` are required,
+    /// the other templates are optional and skipped if missing.
     fn init_template(&mut self) -> Result<()> {
-        try_init_template!(self.renderer, "atom.tpl", self.atom);
         try_init_template!(self.renderer, "base.tpl", self.base);
         try_init_template!(self.renderer, "index.tpl", self.index);
         try_init_template!(self.renderer, "post.tpl", self.post);
-        try_init_template!(self.renderer, "tag.tpl", self.tag);
-        try_init_template!(self.renderer, "tags.tpl", self.tags);
+        try_init_optional_template!(self.renderer, "atom.tpl", self.atom);
+        try_init_optional_template!(self.renderer, "page.tpl", self.page);
+        try_init_optional_template!(self.renderer, "maintenance.tpl", self.maintenance);
+        try_init_optional_template!(self.renderer, "tag.tpl", self.tag);
+        try_init_optional_template!(self.renderer, "tags.tpl", self.tags);
         Ok(())
     }
 
+    /// check the theme has the template of `name`.
+    pub fn has_template(&self, name: &str) -> bool {
+        self.renderer.get_template_names().any(|n| n == name)
+    }
+
     /// create theme directory.
     pub fn init_dir(&self, name: &str) -> Result<()> {
         let dest_dir = self.root.join(name);
@@ -125,36 +183,189 @@ impl Theme {
         }
         debug!("init theme({}) ...", name);
         write_file(&dest_dir.join("static/main.css"), &self.main_css)?;
+        if !self.main_dark_css.is_empty() {
+            write_file(&dest_dir.join("static/main-dark.css"), &self.main_dark_css)?;
+        }
         write_file(&dest_dir.join("static/bundle.js"), &self.main_js)?;
 
         write_file(&dest_dir.join("static/pkg/blog_wasm_bg.wasm"), &self.main_wasm)?;
         write_file(&dest_dir.join("static/pkg/blog_wasm_bg.js"), &self.main_wasm_bg)?;
 
-        write_file(&dest_dir.join("templates/atom.tpl"), &self.atom)?;
         write_file(&dest_dir.join("templates/base.tpl"), &self.base)?;
         write_file(&dest_dir.join("templates/index.tpl"), &self.index)?;
         write_file(&dest_dir.join("templates/post.tpl"), &self.post)?;
-        write_file(&dest_dir.join("templates/tag.tpl"), &self.tag)?;
-        write_file(&dest_dir.join("templates/tags.tpl"), &self.tags)?;
+        if !self.atom.is_empty() {
+            write_file(&dest_dir.join("templates/atom.tpl"), &self.atom)?;
+        }
+        if !self.page.is_empty() {
+            write_file(&dest_dir.join("templates/page.tpl"), &self.page)?;
+        }
+        if !self.maintenance.is_empty() {
+            write_file(&dest_dir.join("templates/maintenance.tpl"), &self.maintenance)?;
+        }
+        if !self.tag.is_empty() {
+            write_file(&dest_dir.join("templates/tag.tpl"), &self.tag)?;
+        }
+        if !self.tags.is_empty() {
+            write_file(&dest_dir.join("templates/tags.tpl"), &self.tags)?;
+        }
         Ok(())
     }
 
-    /// export theme static files.
+    /// export the bundled `simple` theme templates and static files into the new directory `dir`.
+    pub fn export_simple<P: AsRef<Path>>(dir: P) -> Result<()> {
+        let dir = dir.as_ref();
+        if dir.exists() {
+            return Err(Error::ThemeExisted(dir.display().to_string()));
+        }
+        let root = dir.parent().unwrap_or_else(|| Path::new(""));
+        let name = dir.file_name().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+        // the absent `dir` loads the bundled theme
+        let theme = Theme::from_dir(root, "simple", dir)?;
+        theme.init_dir(&name)?;
+        info!(path:% = dir.display(); "exported theme(simple) to {}", dir.display());
+        Ok(())
+    }
+
+    /// concatenate the theme static css files into `static/all.css` and the js files into `static/all.js`.
+    ///
+    /// the files listed in `order` are concatenated first in the order, then the others by name,
+    /// the alternate `static/main-dark.css` and the `static/pkg` modules are never bundled.
+    pub fn bundle_assets(&mut self, order: &[String]) -> Result<()> {
+        let mut names = Vec::new();
+        let static_dir = self.dir.join("static");
+        if static_dir.is_dir() {
+            for entry in WalkDir::new(&static_dir).sort_by_file_name() {
+                let entry = entry.expect("get walker entry error");
+                let rel_path = entry.path().strip_prefix(&self.dir)?.to_string_lossy().replace('\\', "/");
+                let is_asset = rel_path.ends_with(".css") || rel_path.ends_with(".js");
+                if entry.file_type().is_file()
+                    && is_asset
+                    && rel_path != "static/main-dark.css"
+                    && !rel_path.starts_with("static/pkg/")
+                {
+                    names.push(rel_path);
+                }
+            }
+        }
+        for name in order {
+            if !names.contains(name) {
+                warn!("bundle_order file {} not found in theme({})", name, self.name);
+            }
+        }
+        names.sort_by_key(|name| order.iter().position(|o| o == name).unwrap_or(order.len()));
+
+        self.bundles.clear();
+        self.bundled.clear();
+        for name in names {
+            let mut content = Vec::new();
+            read_file(&self.dir.join(&name), &mut content)?;
+            let bundle = if name.ends_with(".css") { "static/all.css" } else { "static/all.js" };
+            let buf = self.bundles.entry(bundle.to_string()).or_default();
+            if !buf.is_empty() {
+                buf.push(b'\n');
+            }
+            buf.extend_from_slice(&content);
+            self.bundled.insert(name, bundle.to_string());
+        }
+        Ok(())
+    }
+
+    /// the content of theme `static/main.css` file.
+    pub fn main_css(&self) -> &[u8] {
+        &self.main_css
+    }
+
+    /// the static css/js files and their exported paths relative to the build directory.
+    ///
+    /// the theme `static` directory is exported as `static_dir`,
+    /// the `cache_bust` mode adds a short hash of the file content into the path:
+    /// * `filename`: `static/main.css` is exported as `static/main.1a2b3c4d.css`
+    /// * `query`: `static/main.css` is linked as `static/main.css?v=1a2b3c4d`
+    ///
+    /// the files bundled by `Theme::bundle_assets` are linked as their bundle.
+    pub fn static_manifest(&self, cache_bust: &str, static_dir: &str) -> BTreeMap<String, String> {
+        let files: &[(&str, &[u8])] = &[
+            ("static/main.css", &self.main_css[..]),
+            ("static/main-dark.css", &self.main_dark_css[..]),
+            ("static/bundle.js", &self.main_js[..]),
+        ];
+        let bundles = self.bundles.iter().map(|(name, buf)| (name.as_str(), &buf[..]));
+        let mut manifest = BTreeMap::new();
+        for (name, buf) in files.iter().copied().chain(bundles) {
+            let path = static_dest(name, static_dir);
+            let dest = match cache_bust {
+                "filename" | "true" => hashed_file_name(&path, buf),
+                "query" => hashed_query(&path, buf),
+                _ => path,
+            };
+            manifest.insert(name.to_string(), dest);
+        }
+        for (name, bundle) in &self.bundled {
+            let dest = manifest[bundle].clone();
+            manifest.insert(name.clone(), dest);
+        }
+        manifest
+    }
+
+    /// export theme static files with the default `cache_bust` and `static_dir` settings.
     pub fn export_static<P: AsRef<Path>>(&self, root: P) -> Result<()> {
+        let settings = Settings::default();
+        self.export_static_files(root, &settings.cache_bust, &settings.static_dir, &mut BTreeMap::new())
+    }
+
+    /// export theme static files.
+    ///
+    /// `shared` maps the content hashes of the files exported by the build to their paths,
+    /// a file identical to an exported one is hard linked instead of written again, e.g. in multiple themes build.
+    pub fn export_static_files<P: AsRef<Path>>(
+        &self,
+        root: P,
+        cache_bust: &str,
+        static_dir: &str,
+        shared: &mut BTreeMap<u32, PathBuf>,
+    ) -> Result<()> {
         debug!("exporting theme({}) static ...", self.name);
-        let src_dir = self.root.join(&self.name);
+        let src_dir = &self.dir;
         let dest_dir = root.as_ref();
-        try_write_file!(src_dir, dest_dir, "static/main.css", &self.main_css);
-        try_write_file!(src_dir, dest_dir, "static/bundle.js", &self.main_js);
+        let manifest = self.static_manifest(cache_bust, static_dir);
+        // the query string of `query` cache bust mode is not part of the file name
+        let dest = |name: &str| manifest[name].split('?').next().unwrap_or(name).to_string();
+        let css = "static/main.css";
+        if !self.bundled.contains_key(css) {
+            try_write_file!(src_dir, dest_dir, css, dest(css), &self.main_css, shared);
+        }
+        let dark_css = "static/main-dark.css";
+        try_write_file!(src_dir, dest_dir, dark_css, dest(dark_css), &self.main_dark_css, shared);
+        let js = "static/bundle.js";
+        if !self.bundled.contains_key(js) {
+            try_write_file!(src_dir, dest_dir, js, dest(js), &self.main_js, shared);
+        }
+        for (name, buf) in &self.bundles {
+            write_shared_file(&dest_dir.join(dest(name)), buf, shared)?;
+        }
 
-        try_write_file!(src_dir, dest_dir, "static/pkg/blog_wasm_bg.wasm", &self.main_wasm);
-        try_write_file!(src_dir, dest_dir, "static/pkg/blog_wasm_bg.js", &self.main_wasm_bg);
+        let wasm = "static/pkg/blog_wasm_bg.wasm";
+        try_write_file!(src_dir, dest_dir, wasm, static_dest(wasm, static_dir), &self.main_wasm, shared);
+        let wasm_bg = "static/pkg/blog_wasm_bg.js";
+        try_write_file!(src_dir, dest_dir, wasm_bg, static_dest(wasm_bg, static_dir), &self.main_wasm_bg, shared);
 
         Ok(())
     }
 }
 
+/// the exported path of theme static file `name`, its `static` directory is replaced by `static_dir`,
+/// e.g. `static/main.css` is exported as `assets/main.css`, or `main.css` if `static_dir` is empty.
+fn static_dest(name: &str, static_dir: &str) -> String {
+    let rest = name.strip_prefix("static/").unwrap_or(name);
+    match static_dir.trim_matches('/') {
+        "" => rest.to_string(),
+        dir => format!("{}/{}", dir, rest),
+    }
+}
+
 static SIMPLE_MAIN_CSS: &[u8] = include_bytes!("themes/simple/static/main.css");
+static SIMPLE_MAIN_DARK_CSS: &[u8] = include_bytes!("themes/simple/static/main-dark.css");
 static SIMPLE_MAIN_JS: &[u8] = include_bytes!("themes/simple/static/bundle.js");
 
 static SIMPLE_MAIN_WASM: &[u8] = include_bytes!("themes/simple/static/pkg/blog_wasm_bg.wasm");
@@ -164,6 +375,8 @@ static SIMPLE_ATOM: &[u8] = include_bytes!("themes/simple/templates/atom.tpl");
 static SIMPLE_BASE: &[u8] = include_bytes!("themes/simple/templates/base.tpl");
 static SIMPLE_INDEX: &[u8] = include_bytes!("themes/simple/templates/index.tpl");
 static SIMPLE_POST: &[u8] = include_bytes!("themes/simple/templates/post.tpl");
+static SIMPLE_PAGE: &[u8] = include_bytes!("themes/simple/templates/page.tpl");
+static SIMPLE_MAINTENANCE: &[u8] = include_bytes!("themes/simple/templates/maintenance.tpl");
 static SIMPLE_TAG: &[u8] = include_bytes!("themes/simple/templates/tag.tpl");
 static SIMPLE_TAGS: &[u8] = include_bytes!("themes/simple/templates/tags.tpl");
 
@@ -15,7 +15,7 @@
     <title>{{ post.title }}</title>
     <updated>{{ post.headers.created }}</updated>
     <published>{{ post.headers.created }}</published>
-    <link href="{{ config.site_url }}{{ post.url  | urlencode }}"/>
+    <link href="{% if post.headers.redirect_to %}{{ post.headers.redirect_to }}{% else %}{{ config.site_url }}{{ post.url  | urlencode }}{% endif %}"/>
     <summary>{{ post.headers.description }}</summary>
     <content type="html" xml:lang="en" xml:base="{{ config.site_url }}">
         <![CDATA[
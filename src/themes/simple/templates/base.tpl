@@ -3,9 +3,31 @@
 <head>
   <meta charset="utf-8">
   <meta name="generator" content="mdblog.rs">
+  {%- if config.noindex %}
+  <meta name="robots" content="noindex, nofollow">
+  {%- endif %}
   <meta name="viewport" content="width=device-width,initial-scale=1.0,maximum-scale=1.0,shrink-to-fit=no,user-scalable=0">
   {%- block title %}{% endblock title -%}
-  <link rel="stylesheet" href="{{ config.site_url }}/static/main.css">
+  {%- if main_css %}
+  <style>{{ main_css }}</style>
+  {%- else %}
+  <link rel="stylesheet" href="{{ config.site_url }}/{{ assets['static/main.css'] }}">
+  {%- endif %}
+  {%- if config.max_content_width %}
+  <style>:root { --max-content-width: {{ config.max_content_width }}; }</style>
+  {%- endif %}
+  <link id="dark-css" rel="stylesheet" href="{{ config.site_url }}/{{ assets['static/main-dark.css'] }}" disabled>
+  <script>
+    if (localStorage.getItem('theme') === 'dark') {
+      document.getElementById('dark-css').disabled = false;
+    }
+  </script>
+  {%- if feed_url %}
+  <link rel="alternate" type="application/atom+xml" title="{{ config.site_name }}" href="{{ feed_url }}">
+  {%- endif %}
+  {%- if sitemap_url %}
+  <link rel="sitemap" type="application/xml" href="{{ sitemap_url }}">
+  {%- endif %}
   {%- block css %}{% endblock css -%}
 </head>
 <body>
@@ -13,14 +35,18 @@
   <div class="container">
     <div id="site">
       <div id="site-name">
-        <a href="{{ config.site_url }}/index.html" title="{{ config.site_name }}">{{ config.site_name }}</a>
+        <a href="{{ config.site_url }}{{ home_url }}" title="{{ config.site_name }}">{{ config.site_name }}</a>
       </div>
       <div id="site-motto">{{ config.site_motto }}</div>
     </div>
     <nav id="header-nav">
-      <a href="{{ config.site_url }}/index.html">Blog</a>
-      <a href="{{ config.site_url }}/tags.html">Tags</a>
+      <a {% if page_type == "index" %}class="active" {% endif %}href="{{ config.site_url }}{{ index_url }}">Blog</a>
+      <a {% if page_type == "tags" or page_type == "tag" %}class="active" {% endif %}href="{{ config.site_url }}{{ tags_url }}">Tags</a>
+      {%- for nav_page in pages %}
+      <a {% if page_type == "page" and post.path == nav_page.path %}class="active" {% endif %}href="{{ config.site_url }}{{ nav_page.formatted_path | urlencode }}">{{ nav_page.title }}</a>
+      {%- endfor %}
       <a href="{{ config.site_url }}/atom.xml">Feed</a>
+      <a id="dark-toggle" href="javascript:void(0)">Dark</a>
     </nav>
     <svg id="menu" viewBox="0 0 1024 1024" version="1.1" xmlns="http://www.w3.org/2000/svg">
       <path d="M128 298.666667h768a42.666667 42.666667 0 0 0 0-85.333334H128a42.666667 42.666667 0 0 0 0 85.333334z m768 170.666666H128a42.666667 42.666667 0 0 0 0 85.333334h768a42.666667 42.666667 0 0 0 0-85.333334z m0 256H128a42.666667 42.666667 0 0 0 0 85.333334h768a42.666667 42.666667 0 0 0 0-85.333334z" fill="#fff"></path>
@@ -72,7 +98,47 @@
     });
   }
 
+  function setDarkToggle() {
+    var toggle = document.getElementById('dark-toggle');
+    var darkCss = document.getElementById('dark-css');
+    if (!toggle || !darkCss) {
+      return;
+    }
+
+    toggle.addEventListener('click', function() {
+      darkCss.disabled = !darkCss.disabled;
+      localStorage.setItem('theme', darkCss.disabled ? 'light' : 'dark');
+    });
+  }
+
+  {%- if config.code_copy_button %}
+
+  function setCodeCopy() {
+    document.querySelectorAll('.code-block').forEach(function(block) {
+      var code = block.querySelector('code');
+      if (!code || !navigator.clipboard) {
+        return;
+      }
+
+      var button = document.createElement('button');
+      button.className = 'code-copy';
+      button.textContent = 'Copy';
+      button.addEventListener('click', function() {
+        navigator.clipboard.writeText(code.innerText).then(function() {
+          button.textContent = 'Copied';
+          setTimeout(function() { button.textContent = 'Copy'; }, 1500);
+        });
+      });
+      block.insertBefore(button, block.firstChild);
+    });
+  }
+  {%- endif %}
+
   window.addEventListener('load', setMenu);
+  window.addEventListener('load', setDarkToggle);
+  {%- if config.code_copy_button %}
+  window.addEventListener('load', setCodeCopy);
+  {%- endif %}
 </script>
 {%- block js %}{% endblock js -%}
 </body>
@@ -1,7 +1,7 @@
 {% extends "base.tpl" %}
 
 {% block title %}
-  <title>{{ config.site_name }}</title>
+  <title>{{ page_title }}</title>
 {% endblock title %}
 
 {% block css %}
@@ -10,28 +10,71 @@
     font-size: 1.5rem;
     margin-left: 1rem;
   }
+
+  .featured .title {
+    font-weight: bold;
+  }
+
+  .link-post {
+    font-style: italic;
+  }
+
+  .description {
+    margin: 0.5rem 0 0 1rem;
+    font-size: 0.9rem;
+  }
+
+  .excerpt {
+    margin: 0.5rem 0 0 1rem;
+  }
+
+  .read-more {
+    margin-left: 1rem;
+    font-size: 0.9rem;
+  }
+
+  .index-content {
+    margin-bottom: 1.5rem;
+  }
 </style>
 {% endblock css %}
 
 {% block main %}
+  {%- if index_content %}
+  <div class="index-content">{{ index_content }}</div>
+  {%- endif %}
   <article>
     {%- for post in posts %}
-      <section>
+      <section{% if post.headers.featured %} class="featured"{% endif %}>
         <span>{{ post.headers.created | truncate(length=10, end="") }}</span>
+        {%- if post.headers.redirect_to %}
+        <a class="title link-post" href="{{ post.headers.redirect_to }}">{{ post.title }} →</a>
+        <p class="description">{% if post.description_html %}{{ post.description_html }}{% else %}{{ post.headers.description }}{% endif %}</p>
+        {%- else %}
         <a class="title" href="{{ config.site_url }}{{ post.formatted_path  | urlencode }}">{{ post.title }}</a>
+        {%- endif %}
+        {%- if full_content and not post.headers.redirect_to %}
+        <div class="excerpt">{{ post.content }}</div>
+        {%- elif post.excerpt %}
+        <div class="excerpt">{{ post.excerpt | safe }}</div>
+        <a class="read-more" href="{{ config.site_url }}{{ post.formatted_path  | urlencode }}">{{ config.read_more_text }}</a>
+        {%- elif post.headers.description and not post.headers.redirect_to %}
+        <p class="description">{% if post.description_html %}{{ post.description_html }}{% else %}{{ post.headers.description }}{% endif %}</p>
+        <a class="read-more" href="{{ config.site_url }}{{ post.formatted_path  | urlencode }}">{{ config.read_more_text }}</a>
+        {%- endif %}
       </section>
     {%- endfor %}
   </article>
 
   <div id="pages">
   {%- if page.index > 1 %}
-    <a class="prev" href="{{ index_pages | nth(n=page.index - 2) | get(key='name') | urlencode }}">« Previous</a>
+    <a class="prev" href="{{ config.site_url }}{{ index_pages | nth(n=page.index - 2) | get(key='url') | urlencode }}">« Previous</a>
   {%- endif -%}
     <span class="spacer"></span>
     <span class="info">{{ page.index }} / {{ index_pages | length }}</span>
     <span class="spacer"></span>
   {%- if page.index < index_pages | length %}
-    <a class="next" href="{{ index_pages | nth(n=page.index) | get(key='name') | urlencode }}">Next »</a>
+    <a class="next" href="{{ config.site_url }}{{ index_pages | nth(n=page.index) | get(key='url') | urlencode }}">Next »</a>
   {% endif -%}
   </div>
 {%- endblock main %}
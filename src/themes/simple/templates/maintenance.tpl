@@ -0,0 +1,33 @@
+<!doctype html>
+<html>
+<head>
+  <meta charset="utf-8">
+  <meta name="generator" content="mdblog.rs">
+  <meta name="robots" content="noindex, nofollow">
+  <meta name="viewport" content="width=device-width,initial-scale=1.0,shrink-to-fit=no">
+  <title>{{ page_title }}</title>
+  <style>
+    body {
+      display: flex;
+      min-height: 100vh;
+      margin: 0;
+      align-items: center;
+      justify-content: center;
+      text-align: center;
+      font-family: sans-serif;
+      color: #333;
+    }
+
+    h1 {
+      font-size: 2rem;
+    }
+  </style>
+</head>
+<body>
+  <main>
+    <h1>{{ config.site_name }}</h1>
+    <p>{{ config.site_motto }}</p>
+    <p>Coming soon.</p>
+  </main>
+</body>
+</html>
@@ -0,0 +1,24 @@
+{% extends "base.tpl" %}
+
+{% block title %}
+  <title>{{ page_title }}</title>
+{% endblock title %}
+
+{%- block css %}
+  {%- for href in post.headers.css %}
+  <link rel="stylesheet" href="{{ href }}">
+  {%- endfor %}
+{% endblock css -%}
+
+{% block main %}
+    <h1>{{ post.title }}</h1>
+    <article>
+      {{ post.content }}
+    </article>
+{%- endblock main %}
+
+{% block js %}
+{%- for src in post.headers.js %}
+<script src="{{ src }}"></script>
+{%- endfor %}
+{% endblock js %}
@@ -1,21 +1,28 @@
 {% extends "base.tpl" %}
 
 {% block title %}
-  <title>{{ post.title }}</title>
+  <title>{{ page_title }}</title>
 {% endblock title %}
 
-{%- block css %}{% endblock css -%}
+{%- block css %}
+  {%- for href in post.headers.css %}
+  <link rel="stylesheet" href="{{ href }}">
+  {%- endfor %}
+{% endblock css -%}
 
 {% block main %}
     <h1>{{ post.title }}</h1>
     <article>
       {{ post.content }}
     </article>
+    {%- if post_footer_html %}
+    <div class="post-footer">{{ post_footer_html }}</div>
+    {%- endif %}
     <div id="article-footer">
       {%- if post.headers.tags %}
         <div>
           <svg class="icon" viewBox="0 0 1024 1024" version="1.1" xmlns="http://www.w3.org/2000/svg"><path d="M323.008 786.752c-52.928 0-96-43.072-96-96s43.072-96 96-96 96 43.072 96 96S375.936 786.752 323.008 786.752zM323.008 658.752c-17.632 0-32 14.336-32 32s14.368 32 32 32 32-14.336 32-32S340.64 658.752 323.008 658.752z" p-id="16156" fill="#bfbfbf"></path><path d="M416.096 927.072 284.224 927.072c-159.936 0-186.912-59.232-186.912-192l0-140.8c0-74.272 14.304-96.256 70.72-150.976l327.04-319.904c36.576-35.488 105.888-35.392 142.304-0.096l263.072 256.032c18.336 17.792 28.864 43.552 28.864 70.656 0 27.296-10.656 53.28-29.248 71.264l-290.016 294.592C544.544 880.416 497.216 927.072 416.096 927.072zM566.24 159.488c-10.496 0-20.16 3.52-26.528 9.696l-327.04 319.936c-49.952 48.48-51.36 54.528-51.36 105.152l0 140.8c0 110.272 8.352 128 122.912 128l131.872 0c52.672 0 83.744-28.48 148.992-92.8l26.656-26.144 263.232-268.256c6.784-6.592 10.336-15.808 10.336-25.888 0-9.888-3.424-18.88-9.472-24.736l-263.072-256.032C586.432 163.04 576.736 159.488 566.24 159.488z"></path></svg>
-          {% for name in post.headers.tags %}<a href="{{ config.site_url }}/tags/{{ name | urlencode }}.html">{{ name }}<sup>{{ tag_map[name].num }}</sup></a>{% endfor %}
+          {% for name in post.headers.tags %}<a href="{{ config.site_url }}{{ tag_map[name].url | urlencode }}">{{ name }}<sup>{{ tag_map[name].num }}</sup></a>{% endfor %}
         </div>
       {% endif -%}
         <div>
@@ -53,6 +60,33 @@
         p.splitText(p.data.length - 1).remove();
       }
     }
+    var spans = document.querySelectorAll(".math");
+    for (i = 0; i < spans.length; i++) {
+      el = spans[i];
+      var display = el.classList.contains("math-display");
+      var n = display ? 2 : 1;
+      var tex = el.textContent;
+      katex.render(tex.slice(n, tex.length - n), el, { displayMode: display });
+    }
   });
 </script>
+{%- if config.json_ld %}
+{%- set post_path = post.url | urlencode %}
+{%- set post_url = config.site_url ~ post_path %}
+<script type="application/ld+json">
+  {
+    "@context": "https://schema.org",
+    "@type": "BlogPosting",
+    "headline": {{ post.title | json_encode() }},
+    "datePublished": "{{ post.headers.created }}",
+    "dateModified": "{% if post.headers.updated %}{{ post.headers.updated }}{% else %}{{ post.headers.created }}{% endif %}",
+    "description": {{ post.headers.description | json_encode() }},
+    "author": {% if config.author %}{{ config.author | json_encode() }}{% else %}{{ config.site_name | json_encode() }}{% endif %},
+    "url": {{ post_url | json_encode() }}
+  }
+</script>
+{%- endif %}
+{%- for src in post.headers.js %}
+<script src="{{ src }}"></script>
+{%- endfor %}
 {% endblock js %}
@@ -1,7 +1,7 @@
 {% extends "base.tpl" %}
 
 {% block title %}
-  <title>{{ tag.name }}</title>
+  <title>{{ page_title }}</title>
 {% endblock title %}
 
 {%- block css %}
@@ -10,29 +10,46 @@
     font-size: 1.5rem;
     margin-left: 1rem;
   }
+
+  .tag-description {
+    margin: 0 0 1.5rem 0;
+  }
+
+  .description {
+    margin: 0.5rem 0 1rem 0;
+    font-size: 0.9rem;
+  }
 </style>
 {% endblock css -%}
 
 {% block main %}
   <h1>{{ tag.name }}</h1>
+  {%- if tag.description %}
+  <p class="tag-description">{{ tag.description }}</p>
+  {%- endif %}
   <article>
   {%- for post in posts %}
     <section>
       <span>{{ post.headers.created | truncate(length=10, end="") }}</span>
       <span class="title"><a href="{{ config.site_url }}{{ post.url  | urlencode }}">{{ post.title }}</a></span>
+      {%- if full_content and not post.headers.redirect_to %}
+      <div class="content">{{ post.content }}</div>
+      {%- elif post.headers.description %}
+      <p class="description">{% if post.description_html %}{{ post.description_html }}{% else %}{{ post.headers.description }}{% endif %}</p>
+      {%- endif %}
     </section>
   {%- endfor %}
   </article>
 
   <div id="pages">
   {%- if page.index > 1 %}
-    <a class="prev" href="{{ tag_pages | get(key=tag.name) | nth(n=page.index - 2) | get(key='name') | urlencode }}">« Previous</a>
+    <a class="prev" href="{{ config.site_url }}{{ tag_pages | get(key=tag.name) | nth(n=page.index - 2) | get(key='url') | urlencode }}">« Previous</a>
   {%- endif -%}
     <span class="spacer"></span>
     <span class="info">{{ page.index }} / {{ tag_pages | get(key=tag.name) | length }}</span>
     <span class="spacer"></span>
   {%- if page.index < tag_pages | get(key=tag.name) | length %}
-    <a class="next" href="{{ tag_pages | get(key=tag.name) | nth(n=page.index) | get(key='name') | urlencode }}">Next »</a>
+    <a class="next" href="{{ config.site_url }}{{ tag_pages | get(key=tag.name) | nth(n=page.index) | get(key='url') | urlencode }}">Next »</a>
   {% endif -%}
   </div>
 {%- endblock main %}
@@ -1,7 +1,7 @@
 {% extends "base.tpl" %}
 
 {% block title %}
-  <title>Tags</title>
+  <title>{{ page_title }}</title>
 {% endblock title %}
 
 {%- block css %}
@@ -29,7 +29,7 @@
   <h1>Tags</h1>
   <article>
   {%- for tag in tags %}
-    <a href="{{ config.site_url }}/tags/{{ tag.name | urlencode }}.html">{{ tag.name }}<sup>{{ tag.num }}</sup></a>
+    <a href="{{ config.site_url }}{{ tag.url | urlencode }}">{{ tag.name }}<sup>{{ tag.num }}</sup></a>
   {%- endfor %}
   </article>
 {%- endblock main %}
@@ -0,0 +1,35 @@
+use std::fmt;
+use std::time::Duration;
+
+/// blog build elapsed time of each phase
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Timing {
+    /// load and parse posts, build index and tag pages
+    pub load: Duration,
+    /// render posts
+    pub posts: Duration,
+    /// render index, tags and tag pages
+    pub pages: Duration,
+    /// export media, theme static and extra files
+    pub static_files: Duration,
+    /// write atom feed and alias redirects
+    pub feeds: Duration,
+}
+
+impl Timing {
+    /// the total elapsed time of all phases
+    pub fn total(&self) -> Duration {
+        self.load + self.posts + self.pages + self.static_files + self.feeds
+    }
+}
+
+impl fmt::Display for Timing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{:<16}{:.3}s", "load posts:", self.load.as_secs_f64())?;
+        writeln!(f, "{:<16}{:.3}s", "render posts:", self.posts.as_secs_f64())?;
+        writeln!(f, "{:<16}{:.3}s", "render pages:", self.pages.as_secs_f64())?;
+        writeln!(f, "{:<16}{:.3}s", "export static:", self.static_files.as_secs_f64())?;
+        writeln!(f, "{:<16}{:.3}s", "write feeds:", self.feeds.as_secs_f64())?;
+        writeln!(f, "{:<16}{:.3}s", "total:", self.total().as_secs_f64())
+    }
+}
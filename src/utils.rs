@@ -1,45 +1,847 @@
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
 use std::error::Error as StdError;
+use std::fmt::Write as _;
 use std::fs::File;
 use std::io::{Read, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
-use log::error;
-use pulldown_cmark::{html, Options, Parser};
+use log::{debug, error};
+use pulldown_cmark::escape::escape_href;
+use pulldown_cmark::{html, CodeBlockKind, Event, HeadingLevel, Options, Parser, Tag as CmarkTag};
+use tempfile::NamedTempFile;
+use time::{Date, Month, UtcOffset};
 
-use crate::error::Result;
+use crate::error::{Error, Result};
+use crate::settings::Settings;
 
 /// create the file of `path` and append content
 ///
 /// if parent of `path` does not existed, create it first.
+/// the content is written to a temporary file in the same directory first,
+/// then renamed to `path`, so an interrupted build never leaves a truncated file.
 pub fn write_file(path: &Path, buf: &[u8]) -> Result<()> {
-    if let Some(p) = path.parent() {
-        std::fs::create_dir_all(p)?;
+    let parent = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    std::fs::create_dir_all(parent).map_err(|e| Error::DirCreate(parent.into(), e))?;
+    let write_error = |e| Error::FileWrite(path.into(), e);
+    let mut file = NamedTempFile::new_in(parent).map_err(write_error)?;
+    file.write_all(buf).map_err(write_error)?;
+    // temporary files are only readable by the owner, use the usual file permissions instead
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.as_file()
+            .set_permissions(std::fs::Permissions::from_mode(0o644))
+            .map_err(write_error)?;
     }
-    let mut file = File::create(path)?;
-    file.write_all(buf)?;
+    file.persist(path).map_err(|e| write_error(e.error))?;
+    Ok(())
+}
+
+/// write `buf` to the file of `path` like `write_file`, unless the file already has the same content.
+///
+/// `shared` maps content hashes to the files written before,
+/// `path` is hard linked to the written file of the same content if possible.
+/// the skipped or linked file is touched, so it is not taken as an orphaned output file.
+pub fn write_shared_file(path: &Path, buf: &[u8], shared: &mut BTreeMap<u32, PathBuf>) -> Result<()> {
+    let touch = |path: &Path| -> std::io::Result<()> {
+        std::fs::OpenOptions::new().write(true).open(path)?.set_modified(SystemTime::now())
+    };
+    let hash = content_hash(buf);
+    if std::fs::read(path).map_or(false, |old| old == buf) {
+        touch(path).map_err(|e| Error::FileWrite(path.into(), e))?;
+        shared.entry(hash).or_insert_with(|| path.to_owned());
+        return Ok(());
+    }
+    if let Some(src) = shared.get(&hash) {
+        if std::fs::read(src).map_or(false, |written| written == buf) {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent).map_err(|e| Error::DirCreate(parent.into(), e))?;
+            }
+            let _ = std::fs::remove_file(path);
+            if std::fs::hard_link(src, path).is_ok() {
+                debug!(path:% = path.display(); "linked identical file {} to {}", path.display(), src.display());
+                return Ok(());
+            }
+        }
+    }
+    write_file(path, buf)?;
+    shared.insert(hash, path.to_owned());
     Ok(())
 }
 
 /// read the file content of `path` to `buf`
 pub fn read_file<P: AsRef<Path>>(path: P, buf: &mut Vec<u8>) -> Result<()> {
-    let mut f = File::open(path)?;
-    f.read_to_end(buf)?;
+    let path = path.as_ref();
+    let read_error = |e| Error::FileRead(path.into(), e);
+    let mut f = File::open(path).map_err(read_error)?;
+    f.read_to_end(buf).map_err(read_error)?;
     Ok(())
 }
 
+/// normalize the trailing slash of an internal `link`, e.g. a post or index page url.
+///
+/// * `always`: `/posts/hello.html` is linked as `/posts/hello/`, `/blog/index.html` as `/blog/`
+/// * `never`: `/posts/hello.html` and `/posts/hello/` are linked as `/posts/hello`, `/blog/index.html` as `/blog`
+/// * otherwise: keep the link as it is
+///
+/// the root link `/`, also of `/index.html`, is kept, the links of other files like `/atom.xml` are untouched.
+pub fn normalize_trailing_slash(link: &str, mode: &str) -> String {
+    if link.is_empty() || (mode != "always" && mode != "never") {
+        return link.to_string();
+    }
+    let (path, suffix) = link.split_at(link.find(|c| c == '?' || c == '#').unwrap_or(link.len()));
+    let is_html = match Path::new(path.trim_end_matches('/')).extension() {
+        Some(ext) if ext == "html" => true,
+        Some(_) => return link.to_string(),
+        None => false,
+    };
+    let path = match path.strip_suffix("index.html") {
+        Some(dir) if dir.ends_with('/') => dir,
+        _ if is_html => path.trim_end_matches(".html"),
+        _ => path,
+    };
+    let trimmed = path.trim_end_matches('/');
+    if trimmed.is_empty() {
+        return format!("/{}", suffix);
+    }
+    match mode {
+        "always" => format!("{}/{}", trimmed, suffix),
+        _ => format!("{}{}", trimmed, suffix),
+    }
+}
+
+/// the output file path of the internal `url`, relative to the build directory.
+///
+/// a directory url like `/posts/my-post/` or `/posts/my-post` is written as its `index.html`.
+pub fn url_dest(url: &str) -> PathBuf {
+    let url = url.split(|c| c == '?' || c == '#').next().unwrap_or_default();
+    let url = url.trim_start_matches('/');
+    let trimmed = url.trim_end_matches('/');
+    if url.ends_with('/') || Path::new(trimmed).extension().is_none() {
+        return Path::new(trimmed).join("index.html");
+    }
+    PathBuf::from(url)
+}
+
+/// parse a fixed utc offset, e.g. `UTC`, `+08:00`, `-0530` or `+8`.
+pub fn parse_utc_offset(s: &str) -> Option<UtcOffset> {
+    let s = s.trim();
+    if s.eq_ignore_ascii_case("utc") || s == "Z" {
+        return Some(UtcOffset::UTC);
+    }
+    let sign = match s.chars().next()? {
+        '+' => 1,
+        '-' => -1,
+        _ => return None,
+    };
+    let digits: String = s[1..].chars().filter(|c| *c != ':').collect();
+    if digits.is_empty() || digits.len() > 4 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let (hours, minutes) = if digits.len() <= 2 {
+        (digits.as_str(), "0")
+    } else {
+        digits.split_at(digits.len() - 2)
+    };
+    let hours: i8 = hours.parse().ok()?;
+    let minutes: i8 = minutes.parse().ok()?;
+    UtcOffset::from_hms(sign * hours, sign * minutes, 0).ok()
+}
+
+/// trim the leading blank lines of post `content`.
+pub fn trim_leading_blank_lines(content: &str) -> &str {
+    let mut rest = content;
+    while let Some(i) = rest.find('\n') {
+        if !rest[..i].trim().is_empty() {
+            break;
+        }
+        rest = &rest[i + 1..];
+    }
+    rest
+}
+
+/// split post `content` into headers and body fenced by `---` lines.
+pub fn split_fenced_headers(content: &str) -> Option<(&str, &str)> {
+    let rest = content.strip_prefix("---\n").or_else(|| content.strip_prefix("---\r\n"))?;
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == "---" {
+            return Some((&rest[..offset], &rest[offset + line.len()..]));
+        }
+        offset += line.len();
+    }
+    None
+}
+
+/// split post `content` into headers and body separated by the first blank line.
+pub fn split_blank_line_headers(content: &str) -> Option<(&str, &str)> {
+    let separator = if content.contains("\r\n") { "\r\n\r\n" } else { "\n\n" };
+    content.split_once(separator)
+}
+
+/// split a leading `YYYY-MM-DD-` date prefix off `name`, returns the date and the rest.
+pub fn split_date_prefix(name: &str) -> Option<(&str, &str)> {
+    if name.len() <= 11 || !name.is_char_boundary(11) || name.as_bytes()[10] != b'-' {
+        return None;
+    }
+    let (date, rest) = (&name[..10], &name[11..]);
+    let mut parts = date.split('-');
+    let year: i32 = parts.next().filter(|s| s.len() == 4)?.parse().ok()?;
+    let month: u8 = parts.next().filter(|s| s.len() == 2)?.parse().ok()?;
+    let day: u8 = parts.next().filter(|s| s.len() == 2)?.parse().ok()?;
+    let month = Month::try_from(month).ok()?;
+    Date::from_calendar_date(year, month, day).ok()?;
+    Some((date, rest))
+}
+
+/// format `offset` as a rfc3339 offset, e.g. `+08:00`.
+pub fn format_utc_offset(offset: UtcOffset) -> String {
+    let sign = if offset.is_negative() { '-' } else { '+' };
+    format!("{}{:02}:{:02}", sign, offset.whole_hours().abs(), offset.minutes_past_hour().abs())
+}
+
+/// split the scheme and host of an absolute http(s) `url`, e.g. `https://example.com/a.html`.
+pub fn url_scheme_host(url: &str) -> Option<(&str, &str)> {
+    let (scheme, rest) = url.split_once("://")?;
+    if !scheme.eq_ignore_ascii_case("http") && !scheme.eq_ignore_ascii_case("https") {
+        return None;
+    }
+    let host = rest.split(|c| c == '/' || c == '?' || c == '#').next().unwrap_or("");
+    Some((scheme, host))
+}
+
+/// the `href` attribute values of the links in `html`.
+pub fn html_links(html: &str) -> Vec<String> {
+    let mut links = Vec::new();
+    let mut rest = html;
+    while let Some(i) = rest.find("href=\"") {
+        rest = &rest[i + 6..];
+        let end = rest.find('"').unwrap_or(rest.len());
+        links.push(rest[..end].replace("&amp;", "&"));
+        rest = &rest[end..];
+    }
+    links
+}
+
+/// replace the `attr` attribute values of the html tags in `html`,
+/// `f` returns the new value or `None` to keep the value.
+pub fn replace_attr_values<F>(html: &str, attr: &str, mut f: F) -> String
+where
+    F: FnMut(&str) -> Option<String>,
+{
+    let pattern = format!(" {}=\"", attr);
+    let mut replaced = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(i) = rest.find(&pattern) {
+        replaced.push_str(&rest[..i + pattern.len()]);
+        rest = &rest[i + pattern.len()..];
+        let end = rest.find('"').unwrap_or(rest.len());
+        let value = &rest[..end];
+        match f(&value.replace("&amp;", "&")) {
+            Some(new_value) => replaced.push_str(&escape_html(&new_value)),
+            None => replaced.push_str(value),
+        }
+        rest = &rest[end..];
+    }
+    replaced.push_str(rest);
+    replaced
+}
+
+/// the standard base64 encoding of `buf`.
+pub fn base64_encode(buf: &[u8]) -> String {
+    const CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity((buf.len() + 2) / 3 * 4);
+    for chunk in buf.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (u32::from(b[0]) << 16) | (u32::from(b[1]) << 8) | u32::from(b[2]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(CHARS[((n >> (18 - 6 * i)) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// the mime type of file `path` guessed from its extension.
+pub fn mime_type(path: &Path) -> &'static str {
+    let ext = path.extension().and_then(|s| s.to_str()).unwrap_or("").to_ascii_lowercase();
+    match ext.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        "ico" => "image/x-icon",
+        "css" => "text/css",
+        "js" => "text/javascript",
+        _ => "application/octet-stream",
+    }
+}
+
+/// percent-encode the url `path`, keeping `/` and unreserved characters.
+pub fn percent_encode_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for b in path.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => encoded.push(b as char),
+            _ => encoded.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    encoded
+}
+
+/// escape the html special characters of `s`.
+pub fn escape_html(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// decode the html character references of `s`, unknown references are kept as they are.
+pub fn unescape_html(s: &str) -> String {
+    let mut unescaped = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let decoded = rest.find(';').filter(|end| *end <= 10).and_then(|end| {
+            let c = match &rest[1..end] {
+                "amp" => '&',
+                "lt" => '<',
+                "gt" => '>',
+                "quot" => '"',
+                "apos" => '\'',
+                "nbsp" => ' ',
+                name if name.starts_with("#x") || name.starts_with("#X") => {
+                    std::char::from_u32(u32::from_str_radix(&name[2..], 16).ok()?)?
+                }
+                name if name.starts_with('#') => std::char::from_u32(name[1..].parse().ok()?)?,
+                _ => return None,
+            };
+            Some((c, end + 1))
+        });
+        match decoded {
+            Some((c, len)) => {
+                unescaped.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                unescaped.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+/// the plain text of `html`, tags are stripped, character references are decoded and whitespaces are collapsed.
+pub fn html_to_text(html: &str) -> String {
+    const INLINE_TAGS: &[&str] = &[
+        "a", "abbr", "b", "code", "del", "em", "i", "kbd", "mark", "s", "small", "span", "strong", "sub", "sup",
+    ];
+    let mut text = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = rest.find('>').map_or(rest.len(), |i| i + 1);
+        let name: String = rest[1..end]
+            .trim_start_matches('/')
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_ascii_lowercase();
+        rest = &rest[end..];
+        if name == "script" || name == "style" {
+            let close = format!("</{}", name);
+            let skip = rest.to_ascii_lowercase().find(&close).unwrap_or(rest.len());
+            rest = &rest[skip..];
+            rest = &rest[rest.find('>').map_or(rest.len(), |i| i + 1)..];
+        }
+        if !INLINE_TAGS.contains(&name.as_str()) {
+            text.push(' ');
+        }
+    }
+    text.push_str(rest);
+    unescape_html(&text).split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// decode the percent-encoded `s`, invalid escapes are kept as they are.
+pub fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hi = (bytes[i + 1] as char).to_digit(16);
+            let lo = (bytes[i + 2] as char).to_digit(16);
+            if let (Some(hi), Some(lo)) = (hi, lo) {
+                decoded.push((hi * 16 + lo) as u8);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// find the byte range of the excerpt `separator` in `content`.
+///
+/// the separator is matched case-insensitively with optional whitespace inside,
+/// e.g. `<!-- more -->` matches `<!--more-->` and `<!-- MORE -->`.
+pub fn find_excerpt_separator(content: &str, separator: &str) -> Option<(usize, usize)> {
+    let token: Vec<char> = separator
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect();
+    if token.is_empty() {
+        return None;
+    }
+    for (start, _) in content.char_indices() {
+        let mut matched = 0;
+        for (i, c) in content[start..].char_indices() {
+            if matched > 0 && c.is_whitespace() {
+                continue;
+            }
+            if !c.to_lowercase().eq(std::iter::once(token[matched])) {
+                break;
+            }
+            matched += 1;
+            if matched == token.len() {
+                return Some((start, start + i + c.len_utf8()));
+            }
+        }
+    }
+    None
+}
+
+/// a short FNV-1a hash of `buf`.
+pub fn content_hash(buf: &[u8]) -> u32 {
+    buf.iter()
+        .fold(0x811c_9dc5_u32, |h, b| (h ^ u32::from(*b)).wrapping_mul(0x0100_0193))
+}
+
+/// append a short FNV-1a hash of `buf` to file `name` as query string,
+/// e.g. `static/main.css` becomes `static/main.css?v=1a2b3c4d`.
+pub fn hashed_query(name: &str, buf: &[u8]) -> String {
+    format!("{}?v={:08x}", name, content_hash(buf))
+}
+
+/// insert a short FNV-1a hash of `buf` before the extension of file `name`,
+/// e.g. `static/main.css` becomes `static/main.1a2b3c4d.css`.
+pub fn hashed_file_name(name: &str, buf: &[u8]) -> String {
+    let hash = content_hash(buf);
+    let file_start = name.rfind('/').map(|i| i + 1).unwrap_or(0);
+    match name[file_start..].rfind('.') {
+        Some(i) => format!("{}.{:08x}{}", &name[..file_start + i], hash, &name[file_start + i..]),
+        None => format!("{}.{:08x}", name, hash),
+    }
+}
+
+/// transliterate basic accented latin character to ascii.
+fn transliterate(c: char) -> Option<&'static str> {
+    let s = match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' => "a",
+        'æ' => "ae",
+        'ç' | 'ć' | 'č' => "c",
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ę' | 'ě' => "e",
+        'ì' | 'í' | 'î' | 'ï' | 'ī' => "i",
+        'ñ' | 'ń' | 'ň' => "n",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' => "o",
+        'œ' => "oe",
+        'ù' | 'ú' | 'û' | 'ü' | 'ū' | 'ů' => "u",
+        'ý' | 'ÿ' => "y",
+        'ß' => "ss",
+        'ś' | 'š' => "s",
+        'ź' | 'ż' | 'ž' => "z",
+        'ł' => "l",
+        'ř' => "r",
+        'ť' => "t",
+        'ď' => "d",
+        _ => return None,
+    };
+    Some(s)
+}
+
+/// the canonical slug of `s`, used by tags, permalinks and heading anchors.
+///
+/// the slug is lowercased, basic accented characters are transliterated,
+/// runs of non-alphanumeric characters are replaced with a single hyphen,
+/// and leading/trailing hyphens are trimmed.
+/// if nothing is left, the hex bytes of `s` are used, or `untitled` for empty `s`.
+pub fn slugify(s: &str) -> String {
+    let mut slug = String::with_capacity(s.len());
+    let mut hyphen = false;
+    for c in s.chars().flat_map(char::to_lowercase) {
+        if let Some(t) = transliterate(c) {
+            slug.push_str(t);
+            hyphen = false;
+        } else if c.is_alphanumeric() {
+            slug.push(c);
+            hyphen = false;
+        } else if !hyphen {
+            slug.push('-');
+            hyphen = true;
+        }
+    }
+    let slug = slug.trim_matches('-');
+    if !slug.is_empty() {
+        return slug.to_string();
+    }
+    if s.is_empty() {
+        return String::from("untitled");
+    }
+    s.bytes().fold(String::new(), |mut hex, b| {
+        let _ = write!(hex, "{:02x}", b);
+        hex
+    })
+}
+
+/// shift heading `level` by `offset`, clamped to h6.
+fn offset_heading_level(level: HeadingLevel, offset: u8) -> HeadingLevel {
+    let level = (level as usize + offset as usize).min(6);
+    HeadingLevel::try_from(level).unwrap_or(HeadingLevel::H6)
+}
+
+/// the `<img>` tag of a markdown image with `image_lazy_loading` and `image_class` settings.
+fn image_html(src: &str, title: &str, alt: &str, settings: &Settings) -> String {
+    let mut html = String::from("<img src=\"");
+    let _ = escape_href(&mut html, src);
+    html.push_str("\" alt=\"");
+    html.push_str(&escape_html(alt));
+    html.push('"');
+    if !title.is_empty() {
+        html.push_str(&format!(" title=\"{}\"", escape_html(title)));
+    }
+    if settings.image_lazy_loading {
+        html.push_str(" loading=\"lazy\"");
+    }
+    if !settings.image_class.is_empty() {
+        html.push_str(&format!(" class=\"{}\"", escape_html(&settings.image_class)));
+    }
+    html.push_str(" />");
+    html
+}
+
+/// replace the `$...$` and `$$...$$` math of markdown `content` by placeholders, see `Settings::math`.
+///
+/// returns the replaced content and the placeholders with their math source and html,
+/// math in code spans, fenced and indented code blocks and escaped `\$` are left alone.
+/// an inline math must not start or end with whitespace, or be followed by a digit, so `$5 and $10` is not math.
+fn protect_math(content: &str) -> (String, Vec<(String, String, String)>) {
+    let bytes = content.as_bytes();
+    let len = content.len();
+    let mut replaced = String::with_capacity(len);
+    let mut maths = Vec::new();
+    let mut fence: Option<&str> = None;
+    // an indented line starts a code block after a blank line, not in a paragraph
+    let mut prev_blank = true;
+    let mut indented_code = false;
+    let mut line_start = true;
+    let mut i = 0;
+    while i < len {
+        if line_start {
+            let line_end = content[i..].find('\n').map_or(len, |n| i + n + 1);
+            let line = &content[i..line_end];
+            let trimmed = line.trim_start_matches(' ');
+            let indented = line.len() - trimmed.len() >= 4 || trimmed.starts_with('\t');
+            let blank = trimmed.trim().is_empty();
+            if let Some(marker) = fence {
+                let marker_char = marker.chars().next().unwrap_or('`');
+                let closing = trimmed.trim_end();
+                if !indented && closing.starts_with(marker) && closing.chars().all(|c| c == marker_char) {
+                    fence = None;
+                }
+                replaced.push_str(line);
+                i = line_end;
+                prev_blank = false;
+                continue;
+            }
+            if indented && !blank && (prev_blank || indented_code) {
+                indented_code = true;
+                replaced.push_str(line);
+                i = line_end;
+                prev_blank = false;
+                continue;
+            }
+            if !blank {
+                indented_code = false;
+            }
+            prev_blank = blank;
+            if !indented && (trimmed.starts_with("```") || trimmed.starts_with("~~~")) {
+                let marker_char = trimmed.chars().next().unwrap_or('`');
+                let n = trimmed.chars().take_while(|c| *c == marker_char).count();
+                fence = Some(&trimmed[..n]);
+                replaced.push_str(line);
+                i = line_end;
+                prev_blank = false;
+                continue;
+            }
+            line_start = false;
+        }
+        match bytes[i] {
+            b'\n' => {
+                replaced.push('\n');
+                line_start = true;
+                i += 1;
+            }
+            b'\\' if i + 1 < len && bytes[i + 1].is_ascii_punctuation() => {
+                replaced.push_str(&content[i..i + 2]);
+                i += 2;
+            }
+            b'`' => {
+                let n = bytes[i..].iter().take_while(|b| **b == b'`').count();
+                let run = &content[i..i + n];
+                let mut end = i + n;
+                let mut search = i + n;
+                while let Some(j) = content[search..].find(run) {
+                    let j = search + j;
+                    let after = j + n;
+                    if after < len && bytes[after] == b'`' {
+                        search = after + bytes[after..].iter().take_while(|b| **b == b'`').count();
+                        continue;
+                    }
+                    end = after;
+                    break;
+                }
+                replaced.push_str(&content[i..end]);
+                i = end;
+            }
+            b'$' if content[i..].starts_with("$$") => match content[i + 2..].find("$$") {
+                Some(j) if !content[i + 2..i + 2 + j].trim().is_empty() => {
+                    let end = i + 2 + j + 2;
+                    let token = format!("mdblogmath{}x", maths.len());
+                    let source = content[i..end].to_string();
+                    let html = format!("<span class=\"math math-display\">{}</span>", escape_html(&source));
+                    replaced.push_str(&token);
+                    maths.push((token, source, html));
+                    i = end;
+                }
+                _ => {
+                    replaced.push_str("$$");
+                    i += 2;
+                }
+            },
+            b'$' => match find_inline_math_end(content, i) {
+                Some(end) => {
+                    let token = format!("mdblogmath{}x", maths.len());
+                    let source = content[i..end].to_string();
+                    let html = format!("<span class=\"math math-inline\">{}</span>", escape_html(&source));
+                    replaced.push_str(&token);
+                    maths.push((token, source, html));
+                    i = end;
+                }
+                None => {
+                    replaced.push('$');
+                    i += 1;
+                }
+            },
+            _ => {
+                let c = content[i..].chars().next().unwrap_or_default();
+                replaced.push(c);
+                i += c.len_utf8();
+            }
+        }
+    }
+    (replaced, maths)
+}
+
+/// the end index after the closing `$` of the inline math starting at `start` of `content`.
+///
+/// the inline math ends in the same paragraph.
+fn find_inline_math_end(content: &str, start: usize) -> Option<usize> {
+    let rest = &content[start + 1..];
+    if rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let paragraph_end = rest.find("\n\n").unwrap_or(rest.len());
+    let rest = &rest[..paragraph_end];
+    let mut prev = None;
+    for (j, c) in rest.char_indices() {
+        if c == '$' && prev.map_or(false, |p: char| !p.is_whitespace() && p != '\\') {
+            // `$` followed by a digit is a price, not the end of math
+            if rest[j + 1..].starts_with(|c: char| c.is_ascii_digit()) {
+                return None;
+            }
+            return Some(start + 1 + j + 1);
+        }
+        prev = Some(c);
+    }
+    None
+}
+
 /// the rendered html content of post body port
-pub fn markdown_to_html(content: &str) -> String {
+pub fn markdown_to_html(content: &str, settings: &Settings) -> String {
+    let (content, maths) = if settings.math {
+        protect_math(content)
+    } else {
+        (content.to_string(), Vec::new())
+    };
+    let content = content.as_str();
     let mut opts = Options::empty();
     opts.insert(Options::ENABLE_TABLES);
     opts.insert(Options::ENABLE_FOOTNOTES);
     opts.insert(Options::ENABLE_TASKLISTS);
     opts.insert(Options::ENABLE_STRIKETHROUGH);
+    // code spans and code blocks are never transformed
+    if settings.smart_punctuation {
+        opts.insert(Options::ENABLE_SMART_PUNCTUATION);
+    }
     let mut s = String::with_capacity(content.len() * 3 / 2);
-    let p = Parser::new_ext(content, opts);
-    html::push_html(&mut s, p);
+    let offset = settings.heading_offset;
+    let code_copy = settings.code_copy_button;
+    let rewrite_images = settings.image_lazy_loading || !settings.image_class.is_empty();
+    // the `src`, `title` and `alt` text of the markdown image being rendered
+    let mut image: Option<(String, String, String)> = None;
+    let p = Parser::new_ext(content, opts).flat_map(|event| match event {
+        // markdown images are rewritten for `loading` and `class` attributes, raw html images are left alone
+        Event::Start(CmarkTag::Image(_, dest, title)) if rewrite_images => {
+            image = Some((dest.to_string(), title.to_string(), String::new()));
+            vec![]
+        }
+        Event::End(CmarkTag::Image(..)) if image.is_some() => {
+            let (src, title, alt) = image.take().unwrap_or_default();
+            vec![Event::Html(image_html(&src, &title, &alt, settings).into())]
+        }
+        Event::Text(ref text) | Event::Code(ref text) if image.is_some() => {
+            if let Some((_, _, ref mut alt)) = image {
+                alt.push_str(text);
+            }
+            vec![]
+        }
+        _ if image.is_some() => vec![],
+        Event::Start(CmarkTag::Heading(level, id, classes)) if offset > 0 => {
+            vec![Event::Start(CmarkTag::Heading(offset_heading_level(level, offset), id, classes))]
+        }
+        Event::End(CmarkTag::Heading(level, id, classes)) if offset > 0 => {
+            vec![Event::End(CmarkTag::Heading(offset_heading_level(level, offset), id, classes))]
+        }
+        // code blocks are wrapped in `<div class="code-block" data-lang="...">` for theme copy buttons
+        Event::Start(CmarkTag::CodeBlock(kind)) if code_copy => {
+            let lang = match kind {
+                CodeBlockKind::Fenced(ref info) => info.split_whitespace().next().unwrap_or(""),
+                CodeBlockKind::Indented => "",
+            };
+            let div = format!("<div class=\"code-block\" data-lang=\"{}\">", escape_html(lang));
+            vec![Event::Html(div.into()), Event::Start(CmarkTag::CodeBlock(kind))]
+        }
+        Event::End(CmarkTag::CodeBlock(kind)) if code_copy => {
+            vec![Event::End(CmarkTag::CodeBlock(kind)), Event::Html("</div>\n".into())]
+        }
+        _ => vec![event],
+    });
+    if settings.heading_anchors {
+        html::push_html(&mut s, heading_anchors(p.collect(), &maths).into_iter());
+    } else {
+        html::push_html(&mut s, p);
+    }
+    for (token, _, math) in maths {
+        s = s.replace(&token, &math);
+    }
     s
 }
 
+/// give the headings an `id` anchor of their text slug, see `slugify`, repeated slugs are numbered like `intro-1`.
+///
+/// the math placeholders of `maths` in heading text are slugged by their math source, see `protect_math`.
+fn heading_anchors<'a>(events: Vec<Event<'a>>, maths: &[(String, String, String)]) -> Vec<Event<'a>> {
+    let mut slugs: BTreeMap<String, usize> = BTreeMap::new();
+    let mut out = Vec::with_capacity(events.len());
+    // the index of the heading start event in `out` and the heading text
+    let mut heading: Option<(usize, String)> = None;
+    for event in events {
+        match event {
+            Event::Start(CmarkTag::Heading(_, None, _)) => {
+                heading = Some((out.len(), String::new()));
+                out.push(event);
+            }
+            Event::Text(ref text) | Event::Code(ref text) if heading.is_some() => {
+                if let Some((_, ref mut heading_text)) = heading {
+                    heading_text.push_str(text);
+                }
+                out.push(event);
+            }
+            Event::End(CmarkTag::Heading(level, None, ref classes)) if heading.is_some() => {
+                let (start, mut text) = heading.take().unwrap_or_default();
+                for (token, source, _) in maths {
+                    text = text.replace(token.as_str(), source);
+                }
+                let mut slug = slugify(&text);
+                let n = slugs.entry(slug.clone()).or_insert(0);
+                if *n > 0 {
+                    slug = format!("{}-{}", slug, n);
+                }
+                *n += 1;
+                let class = if classes.is_empty() {
+                    String::new()
+                } else {
+                    format!(" class=\"{}\"", escape_html(&classes.join(" ")))
+                };
+                out[start] = Event::Html(format!("<{} id=\"{}\"{}>", level, escape_html(&slug), class).into());
+                out.push(Event::Html(format!("</{}>\n", level).into()));
+            }
+            _ => out.push(event),
+        }
+    }
+    out
+}
+
+/// render the short markdown `text` into inline html, e.g. an explicit post description.
+///
+/// paragraphs and other block elements are dropped, only their inline content is kept.
+pub fn markdown_inline_to_html(text: &str) -> String {
+    let p = Parser::new_ext(text, Options::ENABLE_STRIKETHROUGH).filter_map(|event| match event {
+        Event::Start(ref tag) | Event::End(ref tag) => match tag {
+            CmarkTag::Emphasis | CmarkTag::Strong | CmarkTag::Strikethrough | CmarkTag::Link(..) => Some(event),
+            _ => None,
+        },
+        Event::SoftBreak | Event::HardBreak => Some(Event::Text(" ".into())),
+        _ => Some(event),
+    });
+    let mut s = String::with_capacity(text.len() * 3 / 2);
+    html::push_html(&mut s, p);
+    s.trim().to_string()
+}
+
+/// sanitize the rendered post `html` by an allowlist, see `Settings::sanitize_html`.
+///
+/// scripts, styles and event handler attributes are removed,
+/// formatting, links, images, code blocks and heading anchors are kept.
+pub fn sanitize_html(html: &str) -> String {
+    let mut builder = ammonia::Builder::default();
+    builder
+        .link_rel(None)
+        .add_url_schemes(&["post"])
+        .add_generic_attributes(&["id", "class"])
+        .add_tags(&["input"])
+        .add_tag_attributes("a", &["rel", "target"])
+        .add_tag_attributes("img", &["loading"])
+        .add_tag_attributes("div", &["data-lang"])
+        .add_tag_attributes("input", &["type", "checked", "disabled"]);
+    builder.clean(html).to_string()
+}
+
 /// basic error reporting, including the "cause chain".
 pub(crate) fn log_error_chain(mut e: &dyn StdError) {
     error!("error: {}", e);
@@ -48,3 +850,142 @@ pub(crate) fn log_error_chain(mut e: &dyn StdError) {
         e = source;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_file_replaces_content() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("posts/hello.html");
+        write_file(&path, b"old").unwrap();
+        write_file(&path, b"new").unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"new");
+        assert_eq!(std::fs::read_dir(dir.path().join("posts")).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn write_file_failed_persist_keeps_old_content() {
+        let dir = tempfile::tempdir().unwrap();
+        // a non-empty directory can not be replaced by the renamed temporary file
+        let path = dir.path().join("hello.html");
+        std::fs::create_dir(&path).unwrap();
+        std::fs::write(path.join("old.html"), b"old").unwrap();
+        assert!(write_file(&path, b"new").is_err());
+        assert_eq!(std::fs::read(path.join("old.html")).unwrap(), b"old");
+        // the temporary file is removed
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn trailing_slash_keeps_root() {
+        for mode in &["always", "never", ""] {
+            assert_eq!(normalize_trailing_slash("/", mode), "/");
+        }
+        assert_eq!(normalize_trailing_slash("/index.html", "always"), "/");
+        assert_eq!(normalize_trailing_slash("/index.html", "never"), "/");
+        assert_eq!(normalize_trailing_slash("/index.html", ""), "/index.html");
+    }
+
+    #[test]
+    fn trailing_slash_always() {
+        assert_eq!(normalize_trailing_slash("/posts/hello.html", "always"), "/posts/hello/");
+        assert_eq!(normalize_trailing_slash("/posts/hello", "always"), "/posts/hello/");
+        assert_eq!(normalize_trailing_slash("/posts/hello/", "always"), "/posts/hello/");
+        assert_eq!(normalize_trailing_slash("/blog/index.html", "always"), "/blog/");
+        assert_eq!(normalize_trailing_slash("/posts/hello.html#intro", "always"), "/posts/hello/#intro");
+    }
+
+    #[test]
+    fn trailing_slash_never() {
+        assert_eq!(normalize_trailing_slash("/posts/hello.html", "never"), "/posts/hello");
+        assert_eq!(normalize_trailing_slash("/posts/hello/", "never"), "/posts/hello");
+        assert_eq!(normalize_trailing_slash("/posts/hello//", "never"), "/posts/hello");
+        assert_eq!(normalize_trailing_slash("/tags/rust/page/2/index.html", "never"), "/tags/rust/page/2");
+    }
+
+    #[test]
+    fn trailing_slash_keeps_other_extensions() {
+        for mode in &["always", "never"] {
+            assert_eq!(normalize_trailing_slash("/atom.xml", mode), "/atom.xml");
+            assert_eq!(normalize_trailing_slash("/static/main.css", mode), "/static/main.css");
+        }
+        assert_eq!(normalize_trailing_slash("/posts/hello.html", ""), "/posts/hello.html");
+    }
+
+    #[test]
+    fn slugify_unicode() {
+        assert_eq!(slugify("Café Über"), "cafe-uber");
+        assert_eq!(slugify("中文 标签"), "中文-标签");
+        assert_eq!(slugify("Rust"), "rust");
+    }
+
+    #[test]
+    fn slugify_punctuation() {
+        assert_eq!(slugify("C++ & Rust!"), "c-rust");
+        assert_eq!(slugify("  hello,   world  "), "hello-world");
+        assert_eq!(slugify("--a--b--"), "a-b");
+    }
+
+    #[test]
+    fn slugify_empty_after_stripping() {
+        assert_eq!(slugify("!!!"), "212121");
+        assert_eq!(slugify("   "), "202020");
+        assert_eq!(slugify(""), "untitled");
+    }
+
+    #[test]
+    fn heading_anchors_are_slugs() {
+        let settings = Settings {
+            heading_anchors: true,
+            ..Settings::default()
+        };
+        let html = markdown_to_html("# Hello, World!\n\n## Hello World\n\n## `code` Über\n", &settings);
+        assert!(html.contains("<h1 id=\"hello-world\">Hello, World!</h1>"), "{}", html);
+        assert!(html.contains("<h2 id=\"hello-world-1\">Hello World</h2>"), "{}", html);
+        assert!(html.contains("<h2 id=\"code-uber\"><code>code</code> Über</h2>"), "{}", html);
+    }
+
+    #[test]
+    fn heading_anchors_are_off_by_default() {
+        let html = markdown_to_html("# Hello\n", &Settings::default());
+        assert_eq!(html, "<h1>Hello</h1>\n");
+    }
+
+    #[test]
+    fn heading_anchors_of_math_headings() {
+        let settings = Settings {
+            heading_anchors: true,
+            math: true,
+            ..Settings::default()
+        };
+        let html = markdown_to_html("## Euler $e^{i\\pi}$ identity\n", &settings);
+        assert_eq!(
+            html,
+            "<h2 id=\"euler-e-i-pi-identity\">Euler <span class=\"math math-inline\">$e^{i\\pi}$</span> identity</h2>\n"
+        );
+    }
+
+    #[test]
+    fn protect_math_skips_indented_code() {
+        let (replaced, maths) = protect_math("text\n\n    let x = $a$;\n\n    $b$\n\n$c$\n");
+        assert_eq!(maths.len(), 1);
+        assert!(replaced.contains("    let x = $a$;\n\n    $b$\n"), "{}", replaced);
+        assert!(!replaced.contains("$c$"), "{}", replaced);
+    }
+
+    #[test]
+    fn protect_math_in_indented_paragraph_continuation() {
+        let (_, maths) = protect_math("text\n    continued $a$\n");
+        assert_eq!(maths.len(), 1);
+    }
+
+    #[test]
+    fn url_dest_of_directory_and_extensionless_urls() {
+        assert_eq!(url_dest("/"), PathBuf::from("index.html"));
+        assert_eq!(url_dest("/posts/hello/"), PathBuf::from("posts/hello/index.html"));
+        assert_eq!(url_dest("/posts/hello"), PathBuf::from("posts/hello/index.html"));
+        assert_eq!(url_dest("/posts/hello.html"), PathBuf::from("posts/hello.html"));
+    }
+}